@@ -2,7 +2,7 @@ use crate::maze;
 
 const MOVEMENT_SPEED: f32 = 4.0;
 
-#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+#[derive(Copy, Clone, Eq, PartialEq, Hash, Debug)]
 pub enum Direction {
     Still,
     Up,
@@ -24,8 +24,13 @@ pub fn reverse_dir(dir: Direction) -> Direction {
 pub struct Actor {
     pub x: i32,
     pub y: i32,
-    progress_to_next_square: f32,
+    /// How far the actor is from (x, y) towards the next tile in its direction
+    pub progress_to_next_square: f32,
     pub move_direction: Direction,
+    /// Multiplier applied to the base movement speed
+    pub speed: f32,
+    /// Draw position at the start of the current tick, for interpolation
+    last_draw_pos: (f32, f32),
 }
 
 // Flip our progress to the next square when we reverse direction
@@ -50,6 +55,8 @@ impl Actor {
             y,
             progress_to_next_square: 0.0,
             move_direction: Direction::Still,
+            speed: 1.0,
+            last_draw_pos: (x as f32, y as f32),
         }
     }
 
@@ -76,6 +83,60 @@ impl Actor {
         (x, y)
     }
 
+    /// The tile the actor is moving towards, which may be off the edge of the maze
+    pub fn tile_ahead(&self) -> (i32, i32) {
+        match self.move_direction {
+            Direction::Up => (self.x, self.y - 1),
+            Direction::Down => (self.x, self.y + 1),
+            Direction::Left => (self.x - 1, self.y),
+            Direction::Right => (self.x + 1, self.y),
+            Direction::Still => (self.x, self.y),
+        }
+    }
+
+    /// Remember the current draw position, to interpolate from during the next tick
+    pub fn save_draw_pos(&mut self) {
+        self.last_draw_pos = self.get_draw_pos();
+    }
+
+    /// Get the draw position a fraction `alpha` of the way from the start of
+    /// the current tick to the current position.
+    /// Don't interpolate if we've wrapped around the edge of the maze.
+    pub fn get_interpolated_draw_pos(&self, alpha: f32) -> (f32, f32) {
+        let (x, y) = self.get_draw_pos();
+        let (last_x, last_y) = self.last_draw_pos;
+        if (x - last_x).abs() > 1.0 || (y - last_y).abs() > 1.0 {
+            return (x, y);
+        }
+        (last_x + (x - last_x) * alpha, last_y + (y - last_y) * alpha)
+    }
+
+    /// Closest distance in tiles between this actor and `other` at any point
+    /// during the current tick, assuming both moved in straight lines from
+    /// their saved draw positions. This catches actors which pass through
+    /// each other between updates, however far they moved.
+    pub fn closest_approach(&self, other: &Actor, maze: &maze::Maze) -> f32 {
+        let (ax, ay) = self.get_interpolated_draw_pos(0.0);
+        let (bx, by) = other.get_interpolated_draw_pos(0.0);
+        let (ax1, ay1) = self.get_draw_pos();
+        let (bx1, by1) = other.get_draw_pos();
+        // Measure the starting offset the short way round the maze, in case
+        // the actors are at either end of a tunnel
+        let wrap = |d: f32, size: i32| {
+            let size = size as f32;
+            d - size * (d / size).round()
+        };
+        let (px, py) = (wrap(ax - bx, maze.width), wrap(ay - by, maze.height));
+        let (vx, vy) = ((ax1 - ax) - (bx1 - bx), (ay1 - ay) - (by1 - by));
+        let speed_sqr = vx * vx + vy * vy;
+        let t = if speed_sqr > 0.0 {
+            (-(px * vx + py * vy) / speed_sqr).clamp(0.0, 1.0)
+        } else {
+            0.0
+        };
+        ((px + t * vx).powi(2) + (py + t * vy).powi(2)).sqrt()
+    }
+
     /// Get the tile coordinates covered by Actor
     /// We include the current discrete tile, as well as the tile in front of
     /// Actor if the progress to the next square is greater than the threshold
@@ -95,8 +156,11 @@ impl Actor {
 
     /// Walk the actor in the specified direction, taking into account the maze and time delta
     /// Return a boolean indicating whether the actor changed discrete position
+    // Each arm handles its pair of directions in full, rather than falling
+    // through to the arms below when a guard fails
+    #[allow(clippy::collapsible_match)]
     pub fn walk(&mut self, direction: Direction, maze: &maze::Maze, time_delta: f32) -> bool {
-        let offset = MOVEMENT_SPEED * time_delta;
+        let offset = MOVEMENT_SPEED * self.speed * time_delta;
         if self.move_direction == Direction::Still {
             self.move_direction = direction;
         }
@@ -259,16 +323,31 @@ impl Actor {
         time_delta: f32,
     ) -> bool {
         self.move_direction = direction;
-        self.progress_to_next_square += MOVEMENT_SPEED * time_delta;
+        self.progress_to_next_square += MOVEMENT_SPEED * self.speed * time_delta;
         self.update_discrete_position(maze)
     }
 
+    /// Turn around on the spot, keeping the same draw position
+    pub fn reverse(&mut self, maze: &maze::Maze) {
+        if self.progress_to_next_square > 0.0 {
+            self.step(maze);
+            self.progress_to_next_square = 1.0 - self.progress_to_next_square;
+        }
+        self.move_direction = reverse_dir(self.move_direction);
+    }
+
     /// Return a boolean indicating whether the actor changed discrete position
     fn update_discrete_position(&mut self, maze: &maze::Maze) -> bool {
         if self.progress_to_next_square < 1.0 {
             return false;
         }
         self.progress_to_next_square = 0.0;
+        self.step(maze);
+        true
+    }
+
+    /// Move to the next tile in the direction of movement, wrapping around the maze
+    fn step(&mut self, maze: &maze::Maze) {
         match self.move_direction {
             Direction::Up => {
                 if self.y == 0 {
@@ -300,6 +379,64 @@ impl Actor {
             }
             _ => {}
         }
-        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_interpolated_draw_pos() {
+        let mut actor = Actor::new(2, 1);
+        actor.move_direction = Direction::Right;
+        actor.progress_to_next_square = 0.2;
+        actor.save_draw_pos();
+        actor.progress_to_next_square = 0.6;
+        pretty_assertions::assert_eq!(actor.get_interpolated_draw_pos(0.0), (2.2, 1.0));
+        pretty_assertions::assert_eq!(actor.get_interpolated_draw_pos(0.5), (2.4, 1.0));
+        pretty_assertions::assert_eq!(actor.get_interpolated_draw_pos(1.0), (2.6, 1.0));
+        // Wrapping through a tunnel jumps straight to the new position
+        actor.set_pos(0, 1);
+        actor.progress_to_next_square = 0.0;
+        pretty_assertions::assert_eq!(actor.get_interpolated_draw_pos(0.5), (0.0, 1.0));
+    }
+
+    #[test]
+    fn test_closest_approach() {
+        let maze = maze::Maze::new(8, 1, vec![maze::Tile::Path; 8], (0, 0));
+        // Head on, swapping tiles within a single update
+        let mut a = Actor::new(2, 0);
+        let mut b = Actor::new(3, 0);
+        a.move_direction = Direction::Right;
+        b.move_direction = Direction::Left;
+        a.save_draw_pos();
+        b.save_draw_pos();
+        a.step(&maze);
+        b.step(&maze);
+        pretty_assertions::assert_eq!((a.get_pos(), b.get_pos()), ((3, 0), (2, 0)));
+        pretty_assertions::assert_eq!(a.closest_approach(&b, &maze), 0.0);
+        // Moving apart
+        a.save_draw_pos();
+        b.save_draw_pos();
+        a.step(&maze);
+        b.step(&maze);
+        pretty_assertions::assert_eq!(a.closest_approach(&b, &maze), 1.0);
+        // Either side of the edge of the maze
+        let a = Actor::new(0, 0);
+        let b = Actor::new(7, 0);
+        pretty_assertions::assert_eq!(a.closest_approach(&b, &maze), 1.0);
+    }
+
+    #[test]
+    fn test_reverse_keeps_draw_pos() {
+        let maze = maze::Maze::new(5, 1, vec![maze::Tile::Path; 5], (0, 0));
+        let mut actor = Actor::new(2, 0);
+        actor.move_direction = Direction::Right;
+        actor.progress_to_next_square = 0.25;
+        actor.reverse(&maze);
+        pretty_assertions::assert_eq!(actor.get_pos(), (3, 0));
+        pretty_assertions::assert_eq!(actor.move_direction, Direction::Left);
+        pretty_assertions::assert_eq!(actor.get_draw_pos(), (2.25, 0.0));
     }
 }
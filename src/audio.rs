@@ -8,6 +8,7 @@ pub struct Audio {
     death_sound: audio::Source,
     beginning_sound: audio::Source,
     eat_ghost_sound: audio::Source,
+    extra_life_sound: audio::Source,
 }
 
 macro_rules! audio_source {
@@ -24,6 +25,31 @@ impl Audio {
             death_sound: audio_source!(ctx, "../resources/death.wav"),
             beginning_sound: audio_source!(ctx, "../resources/beginning.wav"),
             eat_ghost_sound: audio_source!(ctx, "../resources/eatghost.wav"),
+            extra_life_sound: audio_source!(ctx, "../resources/extralife.wav"),
+        }
+    }
+
+    fn sources(&self) -> [&audio::Source; 6] {
+        [
+            &self.chomp_sound,
+            &self.power_pellet_sound,
+            &self.death_sound,
+            &self.beginning_sound,
+            &self.eat_ghost_sound,
+            &self.extra_life_sound,
+        ]
+    }
+
+    /// Pause every sound, to carry on from the same point with `resume`
+    pub fn pause(&self) {
+        for source in self.sources() {
+            source.pause();
+        }
+    }
+
+    pub fn resume(&self) {
+        for source in self.sources() {
+            source.resume();
         }
     }
 
@@ -46,11 +72,15 @@ impl Audio {
         }
     }
 
-    pub fn play_death(&mut self, ctx: &Context) {
+    pub fn stop_chomp(&mut self, ctx: &Context) {
         match self.chomp_sound.stop(ctx) {
             Ok(_) => {}
             Err(err) => eprintln!("Error stopping chomp sound: {}", err),
         }
+    }
+
+    pub fn play_death(&mut self, ctx: &Context) {
+        self.stop_chomp(ctx);
         match self.death_sound.play(ctx) {
             Ok(_) => {}
             Err(err) => eprintln!("Error playing death sound: {}", err),
@@ -78,4 +108,11 @@ impl Audio {
             Err(err) => eprintln!("Error playing eat ghost sound: {}", err),
         }
     }
+
+    pub fn play_extra_life(&mut self, ctx: &Context) {
+        match self.extra_life_sound.play(ctx) {
+            Ok(_) => {}
+            Err(err) => eprintln!("Error playing extra life sound: {}", err),
+        }
+    }
 }
@@ -0,0 +1,177 @@
+use std::collections::HashSet;
+
+use pathfinding::directed::bfs::bfs;
+
+use crate::{actor, brain, game_logic, ghost, maze};
+
+/// Tiles within this many steps of a dangerous ghost are avoided
+const DANGER_DISTANCE: usize = 3;
+/// Only hunt frightened ghosts with at least this much energised time left, in seconds
+const MIN_HUNT_TIME: f32 = 2.0;
+/// Directions in the same order as the tiles from `Maze::neighbours`
+const DIRECTIONS: [actor::Direction; 4] = [
+    actor::Direction::Left,
+    actor::Direction::Right,
+    actor::Direction::Up,
+    actor::Direction::Down,
+];
+
+/// Choose the direction for Munch to move in, from the current state of the game.
+///
+/// Munch heads for the nearest dot or power pellet along a path which keeps
+/// clear of dangerous ghosts, or for the nearest frightened ghost while
+/// energised. If there is no safe path he runs from the closest ghost.
+pub fn choose_direction(game: &game_logic::GameLogic) -> actor::Direction {
+    let maze = &game.maze;
+    let start = nearest_tile(&game.munch, maze);
+    let danger = danger_tiles(game);
+    let prey = if game.energised_time_left() >= MIN_HUNT_TIME {
+        game.ghosts
+            .iter()
+            .filter(|g| g.mode == ghost::Mode::Frightened)
+            .map(|g| g.actor.get_pos())
+            .collect()
+    } else {
+        HashSet::new()
+    };
+    let step = first_step(maze, start, &danger, |pos| prey.contains(pos))
+        .or_else(|| {
+            first_step(maze, start, &danger, |&(x, y)| {
+                matches!(maze.tile(x, y), maze::Tile::Dot | maze::Tile::PowerPellet)
+            })
+        })
+        .or_else(|| flee(game, start));
+    step.and_then(|step| {
+        maze.neighbours(start)
+            .iter()
+            .position(|&pos| pos == step)
+            .map(|i| DIRECTIONS[i])
+    })
+    .unwrap_or(game.direction())
+}
+
+/// The tile Munch is closest to. Planning from here rather than the tile he
+/// last left stops him turning back and forth between two tiles.
+fn nearest_tile(munch: &actor::Actor, maze: &maze::Maze) -> (i32, i32) {
+    let (x, y) = munch.get_draw_pos();
+    (
+        (x.round() as i32 + maze.width) % maze.width,
+        (y.round() as i32 + maze.height) % maze.height,
+    )
+}
+
+/// Whether a ghost will kill Munch on contact
+fn is_dangerous(ghost: &ghost::Ghost) -> bool {
+    matches!(
+        ghost.mode,
+        ghost::Mode::Chase | ghost::Mode::Scatter | ghost::Mode::LeavingHouse
+    )
+}
+
+/// Tiles a dangerous ghost could reach within `DANGER_DISTANCE` steps
+fn danger_tiles(game: &game_logic::GameLogic) -> HashSet<(i32, i32)> {
+    let maze = &game.maze;
+    let mut danger = HashSet::new();
+    for ghost in game.ghosts.iter().filter(|g| is_dangerous(g)) {
+        let mut frontier = vec![ghost.actor.get_pos()];
+        danger.insert(ghost.actor.get_pos());
+        for _ in 0..DANGER_DISTANCE {
+            frontier = frontier
+                .iter()
+                .flat_map(|&pos| maze.neighbours(pos))
+                .filter(|&(x, y)| maze.is_ghost_passable(x, y))
+                .filter(|&pos| danger.insert(pos))
+                .collect();
+        }
+    }
+    danger
+}
+
+/// The first tile on a shortest safe path from `start` to a tile matching
+/// `success`, or None if there is no such path
+fn first_step(
+    maze: &maze::Maze,
+    start: (i32, i32),
+    danger: &HashSet<(i32, i32)>,
+    success: impl Fn(&(i32, i32)) -> bool,
+) -> Option<(i32, i32)> {
+    let path = bfs(
+        &start,
+        |&pos| {
+            maze.neighbours(pos)
+                .into_iter()
+                .filter(|&(x, y)| maze.is_player_passable(x, y) && !danger.contains(&(x, y)))
+        },
+        |pos| *pos != start && success(pos),
+    )?;
+    path.get(1).copied()
+}
+
+/// The neighbouring tile furthest from the closest dangerous ghost
+fn flee(game: &game_logic::GameLogic, start: (i32, i32)) -> Option<(i32, i32)> {
+    let maze = &game.maze;
+    let ghosts = game
+        .ghosts
+        .iter()
+        .filter(|g| is_dangerous(g))
+        .map(|g| g.actor.get_pos())
+        .collect::<Vec<_>>();
+    maze.neighbours(start)
+        .into_iter()
+        .filter(|&(x, y)| maze.is_player_passable(x, y))
+        .max_by_key(|pos| {
+            ghosts
+                .iter()
+                .map(|ghost| brain::dist_sqr(pos, ghost))
+                .min()
+                .unwrap_or(u32::MAX)
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config;
+
+    #[test]
+    fn test_bot_clears_maze() {
+        let maze_str = "
+#########
+#.......#
+#.##.##.#
+#...M...#
+#.##.##.#
+#...*...#
+####R####
+#########
+";
+        let mut game = game_logic::GameLogic::new(config::Config::from_string(maze_str).unwrap());
+        for _ in 0..60 * game_logic::TICK_RATE {
+            game.set_direction(choose_direction(&game));
+            game.tick();
+            if game.is_level_complete() {
+                break;
+            }
+        }
+        assert!(game.is_level_complete());
+    }
+
+    #[test]
+    fn test_bot_runs_from_ghost() {
+        let maze_str = "
+###########
+#.. M.    #
+#####R#####
+#####B#####
+###########
+";
+        let mut game = game_logic::GameLogic::new(config::Config::from_string(maze_str).unwrap());
+        game.ghosts[0].actor.set_pos(2, 1);
+        pretty_assertions::assert_eq!(choose_direction(&game), actor::Direction::Right);
+        game.ghosts[0].actor.set_pos(7, 1);
+        pretty_assertions::assert_eq!(choose_direction(&game), actor::Direction::Left);
+        // Ghosts in the house are harmless, so Munch goes for the nearest dot
+        game.ghosts[0].mode = ghost::Mode::InHouse;
+        pretty_assertions::assert_eq!(choose_direction(&game), actor::Direction::Right);
+    }
+}
@@ -0,0 +1,335 @@
+use crate::{actor, ghost, level, maze};
+
+/// What a ghost can see of the game when deciding where to go
+pub struct WorldView<'a> {
+    pub maze: &'a maze::Maze,
+    pub munch: &'a actor::Actor,
+    /// Positions of every ghost at the start of the update, including the one deciding
+    pub ghosts: &'a [(ghost::Personality, (i32, i32))],
+}
+
+impl WorldView<'_> {
+    pub fn ghost_pos(&self, personality: ghost::Personality) -> Option<(i32, i32)> {
+        self.ghosts
+            .iter()
+            .find(|(p, _)| *p == personality)
+            .map(|&(_, pos)| pos)
+    }
+}
+
+/// Decides where a ghost heads while chasing or scattering, and how it
+/// picks its way there. Each ghost is given a brain for its personality and
+/// the level by `for_personality` when it spawns.
+pub trait GhostBrain {
+    /// Tile to head for while chasing Munch
+    fn chase_target(&self, ghost: &actor::Actor, world: &WorldView) -> (i32, i32);
+
+    /// Tile to head for while scattering, usually a corner of the maze
+    fn scatter_target(&self, world: &WorldView) -> (i32, i32);
+
+    /// Pick a move for `ghost` from `options`, each a next position and the
+    /// direction to reach it. By default take the one closest to `target` in a
+    /// straight line, preferring earlier options on a tie.
+    fn choose_direction(
+        &self,
+        _ghost: &actor::Actor,
+        _world: &WorldView,
+        options: &[((i32, i32), actor::Direction)],
+        target: (i32, i32),
+    ) -> Option<actor::Direction> {
+        options
+            .iter()
+            .min_by_key(|(pos, _)| dist_sqr(pos, &target))
+            .map(|&(_, dir)| dir)
+    }
+}
+
+/// The brain of one of the four classic ghosts on `level`
+pub fn for_personality(personality: ghost::Personality, level: u32) -> Box<dyn GhostBrain> {
+    match personality {
+        ghost::Personality::Blinky => Box::new(Blinky),
+        ghost::Personality::Pinky => Box::new(Pinky),
+        ghost::Personality::Inky => Box::new(Inky),
+        ghost::Personality::Clyde => Box::new(Clyde {
+            shy_distance: level::level_params(level).clyde_shy_distance,
+        }),
+    }
+}
+
+/// Squared distance between two points
+pub fn dist_sqr(a: &(i32, i32), b: &(i32, i32)) -> u32 {
+    let dx = a.0 as isize - b.0 as isize;
+    let dy = a.1 as isize - b.1 as isize;
+    (dx * dx + dy * dy) as u32
+}
+
+/// Blinky directly targets the player's current position.
+pub struct Blinky;
+
+impl GhostBrain for Blinky {
+    fn chase_target(&self, _ghost: &actor::Actor, world: &WorldView) -> (i32, i32) {
+        world.munch.get_pos()
+    }
+
+    fn scatter_target(&self, world: &WorldView) -> (i32, i32) {
+        (world.maze.width - 1, 0)
+    }
+}
+
+const PINKY_LOOKAHEAD: i32 = 4;
+
+/// Pinky tries to move towards the tile four spaces ahead of the player.
+pub struct Pinky;
+
+impl GhostBrain for Pinky {
+    fn chase_target(&self, _ghost: &actor::Actor, world: &WorldView) -> (i32, i32) {
+        get_lookahead_target(world.munch, world.maze, PINKY_LOOKAHEAD)
+    }
+
+    fn scatter_target(&self, _world: &WorldView) -> (i32, i32) {
+        (0, 0)
+    }
+}
+
+const INKY_LOOKAHEAD: i32 = 2;
+
+/// Inky targets a position based on the player's position and Blinky's position.
+pub struct Inky;
+
+impl GhostBrain for Inky {
+    fn chase_target(&self, _ghost: &actor::Actor, world: &WorldView) -> (i32, i32) {
+        let blinky_pos = world
+            .ghost_pos(ghost::Personality::Blinky)
+            .unwrap_or((0, 0));
+        let maze = world.maze;
+        let (mut x, mut y) = get_lookahead_target(world.munch, maze, INKY_LOOKAHEAD);
+        if x < blinky_pos.0 {
+            x += maze.width + x - blinky_pos.0;
+        } else {
+            x += x - blinky_pos.0;
+        }
+        if y < blinky_pos.1 {
+            y += maze.height + y - blinky_pos.1;
+        } else {
+            y += y - blinky_pos.1;
+        }
+        (x % maze.width, y % maze.height)
+    }
+
+    fn scatter_target(&self, world: &WorldView) -> (i32, i32) {
+        (world.maze.width - 1, world.maze.height - 1)
+    }
+}
+
+/// Clyde chases the player until he gets close, then retreats to his corner.
+pub struct Clyde {
+    /// How close Clyde gets before retreating, in tiles
+    pub shy_distance: i32,
+}
+
+impl GhostBrain for Clyde {
+    fn chase_target(&self, ghost: &actor::Actor, world: &WorldView) -> (i32, i32) {
+        let shy_dist_sqr = (self.shy_distance * self.shy_distance) as u32;
+        if dist_sqr(&world.munch.get_pos(), &ghost.get_pos()) <= shy_dist_sqr {
+            self.scatter_target(world)
+        } else {
+            world.munch.get_pos()
+        }
+    }
+
+    fn scatter_target(&self, world: &WorldView) -> (i32, i32) {
+        (0, world.maze.height - 1)
+    }
+}
+
+/// The tile `lookahead` spaces ahead of the player, or the furthest
+/// passable tile short of that
+#[allow(clippy::collapsible_match)]
+fn get_lookahead_target(munch: &actor::Actor, maze: &maze::Maze, lookahead: i32) -> (i32, i32) {
+    let (mut x, mut y) = munch.get_pos();
+    for i in (1..lookahead + 1).rev() {
+        match munch.move_direction {
+            actor::Direction::Up => {
+                if y < i {
+                    y += maze.height;
+                }
+                if maze.is_ghost_passable(x, y - i) {
+                    return (x, y - i);
+                }
+            }
+            actor::Direction::Down => {
+                if maze.is_ghost_passable(x, y + i) {
+                    return (x, y + i);
+                }
+            }
+            actor::Direction::Left => {
+                if x < i {
+                    x += maze.width;
+                }
+                if maze.is_ghost_passable(x - i, y) {
+                    return (x - i, y);
+                }
+            }
+            actor::Direction::Right => {
+                if maze.is_ghost_passable(x + i, y) {
+                    return (x + i, y);
+                }
+            }
+            _ => {}
+        }
+    }
+    (x % maze.width, y % maze.height)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config;
+
+    #[test]
+    fn test_pinky_target() {
+        let maze_str = "
+###########
+#         #
+#    R    #
+#M       .#
+###########
+";
+        let maze = config::Config::from_string(maze_str).unwrap().maze;
+        let mut munch = actor::Actor::new(4, 1);
+        munch.move_direction = actor::Direction::Right;
+        let world = WorldView {
+            maze: &maze,
+            munch: &munch,
+            ghosts: &[],
+        };
+        let pinky = actor::Actor::new(1, 1);
+        let target = Pinky.chase_target(&pinky, &world);
+        pretty_assertions::assert_eq!(target, (4 + PINKY_LOOKAHEAD, 1));
+        munch.move_direction = actor::Direction::Down;
+        let world = WorldView {
+            maze: &maze,
+            munch: &munch,
+            ghosts: &[],
+        };
+        let target = Pinky.chase_target(&pinky, &world);
+        pretty_assertions::assert_eq!(target, (4, 3));
+    }
+
+    #[test]
+    fn test_inky_target() {
+        let maze_str = "
+###########
+#         #
+#         #
+#    R    #
+#         #
+#M       .#
+###########
+";
+        let maze = config::Config::from_string(maze_str).unwrap().maze;
+        let inky = actor::Actor::new(1, 1);
+        let mut munch = actor::Actor::new(4, 3);
+        munch.move_direction = actor::Direction::Right;
+        let world = WorldView {
+            maze: &maze,
+            munch: &munch,
+            ghosts: &[(ghost::Personality::Blinky, (2, 2))],
+        };
+        pretty_assertions::assert_eq!(Inky.chase_target(&inky, &world), (10, 4));
+        let mut munch = actor::Actor::new(4, 1);
+        munch.move_direction = actor::Direction::Up;
+        let world = WorldView {
+            maze: &maze,
+            munch: &munch,
+            ghosts: &[(ghost::Personality::Blinky, (1, 4))],
+        };
+        pretty_assertions::assert_eq!(Inky.chase_target(&inky, &world), (7, 5));
+    }
+
+    /// Keeps as far from Munch as it can, wherever it's meant to be going
+    struct Shy;
+
+    impl GhostBrain for Shy {
+        fn chase_target(&self, _ghost: &actor::Actor, world: &WorldView) -> (i32, i32) {
+            world.munch.get_pos()
+        }
+
+        fn scatter_target(&self, _world: &WorldView) -> (i32, i32) {
+            (0, 0)
+        }
+
+        fn choose_direction(
+            &self,
+            _ghost: &actor::Actor,
+            world: &WorldView,
+            options: &[((i32, i32), actor::Direction)],
+            _target: (i32, i32),
+        ) -> Option<actor::Direction> {
+            options
+                .iter()
+                .max_by_key(|(pos, _)| dist_sqr(pos, &world.munch.get_pos()))
+                .map(|&(_, dir)| dir)
+        }
+    }
+
+    #[test]
+    fn test_brain_chooses_direction_from_world() {
+        let maze = config::Config::from_string(
+            "
+#######
+#M    #
+### ###
+#.  R #
+#######
+",
+        )
+        .unwrap()
+        .maze;
+        let munch = actor::Actor::new(1, 1);
+        let world = WorldView {
+            maze: &maze,
+            munch: &munch,
+            ghosts: &[],
+        };
+        let mut rng = crate::rng::Rng::new(0);
+        let mut ghost = ghost::Ghost::new(3, 1, ghost::Personality::Blinky);
+        ghost.actor.move_direction = actor::Direction::Down;
+        ghost.generate_next_tile(&world, &mut rng);
+        pretty_assertions::assert_eq!(ghost.actor.move_direction, actor::Direction::Left);
+        ghost.brain = Box::new(Shy);
+        ghost.actor.move_direction = actor::Direction::Down;
+        ghost.generate_next_tile(&world, &mut rng);
+        pretty_assertions::assert_eq!(ghost.actor.move_direction, actor::Direction::Right);
+    }
+
+    #[test]
+    fn test_clyde_retreats_when_close() {
+        let maze = config::Config::from_string(
+            "
+####################
+#M                R#
+#.                 #
+####################
+",
+        )
+        .unwrap()
+        .maze;
+        let munch = actor::Actor::new(1, 1);
+        let world = WorldView {
+            maze: &maze,
+            munch: &munch,
+            ghosts: &[],
+        };
+        let clyde = for_personality(ghost::Personality::Clyde, 1);
+        let far = actor::Actor::new(18, 1);
+        pretty_assertions::assert_eq!(clyde.chase_target(&far, &world), (1, 1));
+        let near = actor::Actor::new(5, 1);
+        pretty_assertions::assert_eq!(clyde.chase_target(&near, &world), (0, 3));
+        // Clyde is bolder on later levels
+        let seven_away = actor::Actor::new(8, 1);
+        pretty_assertions::assert_eq!(clyde.chase_target(&seven_away, &world), (0, 3));
+        let clyde = for_personality(ghost::Personality::Clyde, 5);
+        pretty_assertions::assert_eq!(clyde.chase_target(&seven_away, &world), (1, 1));
+    }
+}
@@ -1,109 +1,421 @@
 use crate::{ghost, maze};
 
-pub const TILE_SIZE: f32 = 48.0;
+pub const DEFAULT_LIVES: u32 = 3;
+pub const DEFAULT_EXTRA_LIFE: u32 = 10_000;
+pub const DEFAULT_COLLISION_RADIUS: f32 = 0.5;
 
+#[derive(Clone)]
 pub struct Config {
     pub maze: maze::Maze,
-    pub player_pos: Option<(i32, i32)>,
+    pub player_pos: (i32, i32),
     pub ghosts_pos: Vec<(i32, i32, ghost::Personality)>,
+    pub starting_lives: u32,
+    /// Score at which Munch earns an extra life, if any. See `GameLogic::extra_life_every`.
+    pub extra_life: Option<u32>,
+    /// Points between further extra lives after the first, if they repeat
+    pub extra_life_every: Option<u32>,
+    /// Scatter and chase phase durations for this maze, replacing those of the
+    /// level. Set by a `schedule` line above the maze, e.g. `schedule 7 20 7 20 5`.
+    /// See `level::LevelParams::schedule`.
+    pub schedule: Option<Vec<f32>>,
+    /// Where bonus fruit appears, if anywhere
+    pub fruit_pos: Option<(i32, i32)>,
+    /// How close Munch and a ghost must come to collide, in tiles
+    pub collision_radius: f32,
 }
 
-fn match_maze_char(c: char) -> Result<maze::Tile, String> {
+/// Location of a run of characters in the maze text.
+/// Lines and columns count from 1, as in a text editor.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Span {
+    pub line: usize,
+    pub column: usize,
+    pub len: usize,
+}
+
+impl Span {
+    fn at(line: usize, column: usize) -> Self {
+        Span {
+            line,
+            column,
+            len: 1,
+        }
+    }
+}
+
+impl std::fmt::Display for Span {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "line {}, column {}", self.line, self.column)
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ConfigError {
+    Empty,
+    UnknownTile {
+        c: char,
+        span: Span,
+    },
+    /// A line with a different length to the first line of the maze
+    RaggedLine {
+        expected: usize,
+        found: usize,
+        span: Span,
+    },
+    DuplicatePlayer {
+        first: Span,
+        second: Span,
+    },
+    DuplicateRespawn {
+        first: Span,
+        second: Span,
+    },
+    DuplicateFruit {
+        first: Span,
+        second: Span,
+    },
+    /// A line above the maze which isn't a known setting
+    UnknownSetting {
+        name: String,
+        span: Span,
+    },
+    /// A value of a setting which isn't a positive number
+    InvalidValue {
+        value: String,
+        expected: &'static str,
+        span: Span,
+    },
+    /// A setting which takes a single value given none or several
+    WrongValueCount {
+        name: String,
+        found: usize,
+        span: Span,
+    },
+    MissingPlayer,
+    MissingRespawn,
+    NoDots,
+}
+
+impl ConfigError {
+    /// The offending part of the maze text, if the error has a location
+    pub fn span(&self) -> Option<Span> {
+        match self {
+            ConfigError::UnknownTile { span, .. }
+            | ConfigError::RaggedLine { span, .. }
+            | ConfigError::UnknownSetting { span, .. }
+            | ConfigError::InvalidValue { span, .. }
+            | ConfigError::WrongValueCount { span, .. } => Some(*span),
+            ConfigError::DuplicatePlayer { second, .. }
+            | ConfigError::DuplicateRespawn { second, .. }
+            | ConfigError::DuplicateFruit { second, .. } => Some(*second),
+            ConfigError::Empty
+            | ConfigError::MissingPlayer
+            | ConfigError::MissingRespawn
+            | ConfigError::NoDots => None,
+        }
+    }
+
+    /// Render the error with the offending line of `source` underlined, e.g.
+    ///
+    /// ```text
+    /// error: unknown tile character '@'
+    ///  --> line 6, column 2
+    ///   |
+    /// 6 | #@###
+    ///   |  ^
+    /// ```
+    pub fn render(&self, source: &str) -> String {
+        let mut out = format!("error: {}", self);
+        let Some(span) = self.span() else {
+            return out;
+        };
+        let Some(line) = source.lines().nth(span.line - 1) else {
+            return out;
+        };
+        let gutter = " ".repeat(span.line.to_string().len());
+        out.push_str(&format!("\n{}--> {}", gutter, span));
+        out.push_str(&format!("\n{} |", gutter));
+        out.push_str(&format!("\n{} | {}", span.line, line));
+        out.push_str(&format!(
+            "\n{} | {}{}",
+            gutter,
+            " ".repeat(span.column - 1),
+            "^".repeat(span.len.max(1))
+        ));
+        out
+    }
+}
+
+impl std::fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            ConfigError::Empty => write!(f, "maze is empty"),
+            ConfigError::UnknownTile { c, .. } => write!(f, "unknown tile character '{}'", c),
+            ConfigError::RaggedLine {
+                expected, found, ..
+            } => write!(
+                f,
+                "inconsistent line length: expected {} characters, found {}",
+                expected, found
+            ),
+            ConfigError::DuplicatePlayer { first, .. } => {
+                write!(f, "second player 'M' tile, the first is at {}", first)
+            }
+            ConfigError::DuplicateRespawn { first, .. } => {
+                write!(f, "second respawn 'R' tile, the first is at {}", first)
+            }
+            ConfigError::DuplicateFruit { first, .. } => {
+                write!(f, "second fruit 'F' tile, the first is at {}", first)
+            }
+            ConfigError::UnknownSetting { name, .. } => write!(f, "unknown setting '{}'", name),
+            ConfigError::InvalidValue {
+                value, expected, ..
+            } => write!(f, "invalid value '{}', expected {}", value, expected),
+            ConfigError::WrongValueCount { name, found, .. } => {
+                write!(f, "'{}' takes a single value, found {}", name, found)
+            }
+            ConfigError::MissingPlayer => write!(f, "no player 'M' tile found"),
+            ConfigError::MissingRespawn => write!(f, "no respawn 'R' tile found"),
+            ConfigError::NoDots => write!(f, "maze has no dots to eat"),
+        }
+    }
+}
+
+impl std::error::Error for ConfigError {}
+
+fn match_maze_char(c: char) -> Option<maze::Tile> {
     match c {
-        '#' => Ok(maze::Tile::Wall),
-        ' ' | 'M' => Ok(maze::Tile::Path),
-        '=' | 'B' | 'P' | 'I' | 'C' => Ok(maze::Tile::PlayerImpassable),
-        'R' => Ok(maze::Tile::Respawn),
-        '.' => Ok(maze::Tile::Dot),
-        '*' => Ok(maze::Tile::PowerPellet),
-        _ => Err(format!("Unknown tile character '{}'", c)),
+        '#' => Some(maze::Tile::Wall),
+        ' ' | 'M' | 'F' => Some(maze::Tile::Path),
+        '=' => Some(maze::Tile::Door),
+        'B' | 'P' | 'I' | 'C' => Some(maze::Tile::PlayerImpassable),
+        'R' => Some(maze::Tile::Respawn),
+        '.' => Some(maze::Tile::Dot),
+        '*' => Some(maze::Tile::PowerPellet),
+        _ => None,
     }
 }
 
+/// Record the position of a tile which may appear at most once
+fn set_unique(
+    first: &mut Option<((i32, i32), Span)>,
+    pos: (i32, i32),
+    span: Span,
+    duplicate: fn(Span, Span) -> ConfigError,
+) -> Result<(), ConfigError> {
+    match first {
+        Some((_, first_span)) => Err(duplicate(*first_span, span)),
+        None => {
+            first.replace((pos, span));
+            Ok(())
+        }
+    }
+}
+
+/// Parse the values of a setting, which must all be positive numbers
+fn parse_values(
+    line_number: usize,
+    line: &str,
+    expected: &'static str,
+) -> Result<Vec<f32>, ConfigError> {
+    line.split_whitespace()
+        .skip(1)
+        .map(|value| {
+            let column = line[..value.as_ptr() as usize - line.as_ptr() as usize]
+                .chars()
+                .count()
+                + 1;
+            value
+                .parse::<f32>()
+                .ok()
+                .filter(|n| n.is_finite() && *n > 0.0)
+                .ok_or_else(|| ConfigError::InvalidValue {
+                    value: value.to_string(),
+                    expected,
+                    span: Span {
+                        line: line_number,
+                        column,
+                        len: value.chars().count(),
+                    },
+                })
+        })
+        .collect()
+}
+
 impl Config {
-    fn add_tile(
-        maze: &mut Vec<maze::Tile>,
-        c: char,
-        respawn_point: &mut Option<(i32, i32)>,
-        x: i32,
-        y: i32,
-    ) -> Result<(), String> {
-        match match_maze_char(c) {
-            Ok(tile) => {
-                if tile == maze::Tile::Respawn {
-                    if respawn_point.is_some() {
-                        return Err("Second respawn tile found".to_string());
+    /// Parse a maze, optionally preceded by lines of settings starting with
+    /// a lower case name
+    pub fn from_string(s: &str) -> Result<Self, ConfigError> {
+        let lines: Vec<(usize, &str)> = s.lines().enumerate().collect();
+        let mut schedule = None;
+        let mut collision_radius = DEFAULT_COLLISION_RADIUS;
+        let mut n_settings = 0;
+        for &(i, line) in &lines {
+            if line.starts_with(|c: char| c.is_ascii_lowercase()) {
+                let name = line.split_whitespace().next().unwrap_or_default();
+                let span = Span {
+                    line: i + 1,
+                    column: 1,
+                    len: name.chars().count(),
+                };
+                match name {
+                    "schedule" => {
+                        schedule = Some(parse_values(i + 1, line, "seconds above 0")?);
+                    }
+                    "collision_radius" => {
+                        match parse_values(i + 1, line, "a distance in tiles above 0")?[..] {
+                            [radius] => collision_radius = radius,
+                            ref values => {
+                                return Err(ConfigError::WrongValueCount {
+                                    name: name.to_string(),
+                                    found: values.len(),
+                                    span,
+                                })
+                            }
+                        }
+                    }
+                    _ => {
+                        return Err(ConfigError::UnknownSetting {
+                            name: name.to_string(),
+                            span,
+                        })
                     }
-                    respawn_point.replace((x, y));
                 }
-                maze.push(tile);
+            } else if !line.trim().is_empty() {
+                break;
             }
-            Err(e) => return Err(e),
+            n_settings += 1;
+        }
+        // Skip blank lines around the maze, keeping track of the line numbers
+        let lines: Vec<(usize, &str)> = lines
+            .into_iter()
+            .skip(n_settings)
+            .skip_while(|(_, line)| line.trim().is_empty())
+            .collect();
+        let n_lines = lines.len()
+            - lines
+                .iter()
+                .rev()
+                .take_while(|(_, line)| line.trim().is_empty())
+                .count();
+        let lines = &lines[..n_lines];
+        let Some((_, first_line)) = lines.first() else {
+            return Err(ConfigError::Empty);
         };
-        Ok(())
-    }
-
-    pub fn from_string(s: &str) -> Result<Self, String> {
-        let lines: Vec<&str> = s.trim().split('\n').collect();
-        let width = lines[0].len();
+        let width = first_line.chars().count();
         let height = lines.len();
         let mut maze = Vec::with_capacity(width * height);
-        let mut player_pos = None;
+        let mut player: Option<((i32, i32), Span)> = None;
+        let mut respawn: Option<((i32, i32), Span)> = None;
+        let mut fruit: Option<((i32, i32), Span)> = None;
         let mut ghosts_pos = Vec::new();
-        let mut respawn_point: Option<(i32, i32)> = None;
-        for (y, line) in lines.iter().enumerate() {
-            if width != line.len() {
-                return Err(format!(
-                    "Inconsistent line length: line 1 has length {}, line {} has length {}",
-                    width,
-                    y + 1,
-                    line.len()
-                ));
+        for (y, (line_index, line)) in lines.iter().enumerate() {
+            let line_number = line_index + 1;
+            let line_width = line.chars().count();
+            if width != line_width {
+                return Err(ConfigError::RaggedLine {
+                    expected: width,
+                    found: line_width,
+                    span: Span {
+                        line: line_number,
+                        column: width.min(line_width) + 1,
+                        len: width.abs_diff(line_width),
+                    },
+                });
             }
             for (x, c) in line.chars().enumerate() {
-                match Self::add_tile(&mut maze, c, &mut respawn_point, x as i32, y as i32) {
-                    Ok(()) => {}
-                    Err(e) => return Err(format!("Error at ({}, {}): {}", x, y, e)),
-                };
+                let span = Span::at(line_number, x + 1);
+                let pos = (x as i32, y as i32);
+                let tile = match_maze_char(c).ok_or(ConfigError::UnknownTile { c, span })?;
+                maze.push(tile);
                 match c {
-                    'M' => {
-                        assert!(player_pos.is_none(), "Multiple player positions found");
-                        player_pos = Some((x as i32, y as i32));
-                    }
-                    'B' => {
-                        ghosts_pos.push((x as i32, y as i32, ghost::Personality::Blinky));
-                    }
-                    'I' => {
-                        ghosts_pos.push((x as i32, y as i32, ghost::Personality::Inky));
-                    }
-                    'P' => {
-                        ghosts_pos.push((x as i32, y as i32, ghost::Personality::Pinky));
-                    }
-                    'C' => {
-                        ghosts_pos.push((x as i32, y as i32, ghost::Personality::Clyde));
-                    }
+                    'M' => set_unique(&mut player, pos, span, |first, second| {
+                        ConfigError::DuplicatePlayer { first, second }
+                    })?,
+                    'R' => set_unique(&mut respawn, pos, span, |first, second| {
+                        ConfigError::DuplicateRespawn { first, second }
+                    })?,
+                    'F' => set_unique(&mut fruit, pos, span, |first, second| {
+                        ConfigError::DuplicateFruit { first, second }
+                    })?,
+                    'B' => ghosts_pos.push((pos.0, pos.1, ghost::Personality::Blinky)),
+                    'I' => ghosts_pos.push((pos.0, pos.1, ghost::Personality::Inky)),
+                    'P' => ghosts_pos.push((pos.0, pos.1, ghost::Personality::Pinky)),
+                    'C' => ghosts_pos.push((pos.0, pos.1, ghost::Personality::Clyde)),
                     _ => {}
                 }
             }
         }
-        match respawn_point {
-            Some(respawn_point) => Ok(Config {
-                maze: maze::Maze::new(width as i32, height as i32, maze, respawn_point),
-                player_pos,
-                ghosts_pos,
-            }),
-            None => Err("No respawn tile found".to_string()),
+        let (player_pos, _) = player.ok_or(ConfigError::MissingPlayer)?;
+        let (respawn_point, _) = respawn.ok_or(ConfigError::MissingRespawn)?;
+        let maze = maze::Maze::new(width as i32, height as i32, maze, respawn_point);
+        if maze.n_dots == 0 {
+            return Err(ConfigError::NoDots);
         }
+        Ok(Config {
+            maze,
+            player_pos,
+            ghosts_pos,
+            starting_lives: DEFAULT_LIVES,
+            extra_life: Some(DEFAULT_EXTRA_LIFE),
+            extra_life_every: None,
+            schedule,
+            fruit_pos: fruit.map(|(pos, _)| pos),
+            collision_radius,
+        })
+    }
+
+    /// Load a maze file, rendering any error with the offending part of the file
+    pub fn from_file(path: &std::path::Path) -> Result<Self, String> {
+        let s = std::fs::read_to_string(path)
+            .map_err(|e| format!("Could not read {}: {}", path.display(), e))?;
+        Self::from_string(&s).map_err(|e| format!("{}\n{}", path.display(), e.render(&s)))
     }
 
     #[cfg(test)]
     pub fn empty() -> Self {
         Config {
             maze: maze::Maze::empty(),
-            player_pos: None,
+            player_pos: (0, 0),
             ghosts_pos: Vec::new(),
+            starting_lives: DEFAULT_LIVES,
+            extra_life: Some(DEFAULT_EXTRA_LIFE),
+            extra_life_every: None,
+            schedule: None,
+            fruit_pos: None,
+            collision_radius: DEFAULT_COLLISION_RADIUS,
+        }
+    }
+}
+
+/// Load a campaign of mazes, played in order of file name, from every `.txt`
+/// file in a directory. A path to a single file is a campaign of one maze.
+pub fn load_campaign(path: &std::path::Path) -> Result<Vec<Config>, String> {
+    campaign_paths(path)?
+        .iter()
+        .map(|p| Config::from_file(p))
+        .collect()
+}
+
+/// Get the maze files of a campaign, in the order they are played
+pub fn campaign_paths(path: &std::path::Path) -> Result<Vec<std::path::PathBuf>, String> {
+    if !path.is_dir() {
+        return Ok(vec![path.to_path_buf()]);
+    }
+    let entries =
+        std::fs::read_dir(path).map_err(|e| format!("Could not read {}: {}", path.display(), e))?;
+    let mut paths = Vec::new();
+    for entry in entries {
+        let entry = entry.map_err(|e| format!("Could not read {}: {}", path.display(), e))?;
+        if entry.path().extension().is_some_and(|ext| ext == "txt") {
+            paths.push(entry.path());
         }
     }
+    if paths.is_empty() {
+        return Err(format!("No .txt maze files found in {}", path.display()));
+    }
+    paths.sort();
+    Ok(paths)
 }
 
 #[cfg(test)]
@@ -132,11 +444,8 @@ mod tests {
             Some(maze::Tile::PlayerImpassable)
         );
         pretty_assertions::assert_eq!(config.maze.get_tile(4, 4), Some(maze::Tile::Wall));
-        pretty_assertions::assert_eq!(
-            config.maze.get_tile(1, 2),
-            Some(maze::Tile::PlayerImpassable)
-        );
-        pretty_assertions::assert_eq!(config.player_pos, Some((2, 1)));
+        pretty_assertions::assert_eq!(config.maze.get_tile(1, 2), Some(maze::Tile::Door));
+        pretty_assertions::assert_eq!(config.player_pos, (2, 1));
         pretty_assertions::assert_eq!(
             config.ghosts_pos,
             vec![
@@ -147,6 +456,82 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_maze_schedule_setting() {
+        let maze_str = "
+schedule 7 20 5.5
+
+#####
+#M.R#
+#####
+";
+        let config = Config::from_string(maze_str).unwrap();
+        pretty_assertions::assert_eq!(config.schedule, Some(vec![7.0, 20.0, 5.5]));
+        pretty_assertions::assert_eq!(config.maze.height, 3);
+        pretty_assertions::assert_eq!(
+            Config::from_string(&maze_str.replace("5.5", "soon")).err(),
+            Some(ConfigError::InvalidValue {
+                value: "soon".to_string(),
+                expected: "seconds above 0",
+                span: Span {
+                    line: 2,
+                    column: 15,
+                    len: 4
+                }
+            })
+        );
+        pretty_assertions::assert_eq!(
+            Config::from_string(&maze_str.replace("schedule", "speed")).err(),
+            Some(ConfigError::UnknownSetting {
+                name: "speed".to_string(),
+                span: Span {
+                    line: 2,
+                    column: 1,
+                    len: 5
+                }
+            })
+        );
+    }
+
+    #[test]
+    fn test_maze_collision_radius_setting() {
+        let maze_str = "
+collision_radius 0.25
+#####
+#M.R#
+#####
+";
+        let config = Config::from_string(maze_str).unwrap();
+        pretty_assertions::assert_eq!(config.collision_radius, 0.25);
+        pretty_assertions::assert_eq!(
+            Config::from_string(&maze_str.replace("0.25", "0.25 1")).err(),
+            Some(ConfigError::WrongValueCount {
+                name: "collision_radius".to_string(),
+                found: 2,
+                span: Span {
+                    line: 2,
+                    column: 1,
+                    len: 16
+                }
+            })
+        );
+        pretty_assertions::assert_eq!(
+            Config::from_string(&maze_str.replace("0.25", "-1"))
+                .err()
+                .map(|e| e.to_string()),
+            Some("invalid value '-1', expected a distance in tiles above 0".to_string())
+        );
+    }
+
+    #[test]
+    fn test_load_campaign() {
+        let campaign = load_campaign(std::path::Path::new("resources")).unwrap();
+        pretty_assertions::assert_eq!(campaign.len(), 1);
+        let single = load_campaign(std::path::Path::new("resources/maze.txt")).unwrap();
+        pretty_assertions::assert_eq!(single[0].maze.to_string(), campaign[0].maze.to_string());
+        assert!(load_campaign(std::path::Path::new("resources/missing.txt")).is_err());
+    }
+
     #[test]
     fn test_maze_invalid_size() {
         let maze_str = "
@@ -158,7 +543,15 @@ mod tests {
         assert!(config.is_err());
         pretty_assertions::assert_eq!(
             config.err().unwrap(),
-            "Inconsistent line length: line 1 has length 5, line 2 has length 4"
+            ConfigError::RaggedLine {
+                expected: 5,
+                found: 4,
+                span: Span {
+                    line: 3,
+                    column: 5,
+                    len: 1
+                }
+            }
         );
     }
 
@@ -173,9 +566,55 @@ mod tests {
 ";
         let config = Config::from_string(maze_str);
         assert!(config.is_err());
+        let error = config.err().unwrap();
         pretty_assertions::assert_eq!(
-            config.err().unwrap(),
-            "Error at (1, 4): Unknown tile character '@'"
+            error,
+            ConfigError::UnknownTile {
+                c: '@',
+                span: Span::at(6, 2)
+            }
+        );
+        pretty_assertions::assert_eq!(
+            error.render(maze_str),
+            "error: unknown tile character '@'
+ --> line 6, column 2
+  |
+6 | #@###
+  |  ^"
+        );
+    }
+
+    #[test]
+    fn test_maze_duplicate_player() {
+        let maze_str = "
+#####
+#M.R#
+#.M.#
+#####
+";
+        pretty_assertions::assert_eq!(
+            Config::from_string(maze_str).err(),
+            Some(ConfigError::DuplicatePlayer {
+                first: Span::at(3, 2),
+                second: Span::at(4, 3)
+            })
+        );
+    }
+
+    #[test]
+    fn test_maze_missing_tiles() {
+        pretty_assertions::assert_eq!(
+            Config::from_string("\n#####\n#..R#\n#####\n").err(),
+            Some(ConfigError::MissingPlayer)
+        );
+        pretty_assertions::assert_eq!(
+            Config::from_string("\n#####\n#M..#\n#####\n").err(),
+            Some(ConfigError::MissingRespawn)
+        );
+        pretty_assertions::assert_eq!(
+            Config::from_string("\n#####\n#M*R#\n#####\n").err(),
+            Some(ConfigError::NoDots)
         );
+        pretty_assertions::assert_eq!(Config::from_string("\n\n").err(), Some(ConfigError::Empty));
     }
 }
@@ -0,0 +1,154 @@
+use crate::{actor, config, game_logic, ghost, maze};
+
+/// Reward given when Munch loses a life, on top of any change in score
+pub const DEATH_PENALTY: f32 = 500.0;
+
+/// What an agent sees of a ghost
+#[derive(Clone, Debug, PartialEq)]
+pub struct GhostObservation {
+    pub personality: ghost::Personality,
+    pub pos: (f32, f32),
+    pub mode: ghost::Mode,
+}
+
+/// The state of the game as seen by an agent after a reset or step
+#[derive(Clone, Debug, PartialEq)]
+pub struct Observation {
+    pub width: i32,
+    pub height: i32,
+    /// Tiles of the maze in rows from the top left, `width * height` long
+    pub tiles: Vec<maze::Tile>,
+    pub munch: (f32, f32),
+    pub ghosts: Vec<GhostObservation>,
+    /// Seconds left until Munch stops being energised, or zero
+    pub energised_time_left: f32,
+    pub score: u32,
+    pub lives: u32,
+    pub level: u32,
+}
+
+/// A reinforcement learning environment wrapped around a headless game.
+///
+/// Each step steers Munch with an action, then simulates `ticks_per_step`
+/// fixed ticks. The reward is the score gained over the step, less
+/// `DEATH_PENALTY` if Munch died. Cleared levels move on to the next maze,
+/// and the episode is done once Munch runs out of lives.
+pub struct Env {
+    game: game_logic::GameLogic,
+    ticks_per_step: u32,
+    done: bool,
+}
+
+impl Env {
+    pub fn new(campaign: Vec<config::Config>, ticks_per_step: u32) -> Self {
+        Env {
+            game: game_logic::GameLogic::with_campaign(campaign),
+            ticks_per_step: ticks_per_step.max(1),
+            done: false,
+        }
+    }
+
+    /// Start a new episode from level 1. Episodes with the same seed and
+    /// actions play out the same way.
+    pub fn reset(&mut self, seed: u64) -> Observation {
+        self.game.set_seed(seed);
+        self.game.restart();
+        self.done = false;
+        self.observe()
+    }
+
+    /// Seed of the current episode
+    pub fn seed(&self) -> u64 {
+        self.game.seed()
+    }
+
+    /// Steer Munch in the direction of `action` and advance the game,
+    /// returning the new observation, the reward and whether the episode is done
+    pub fn step(&mut self, action: actor::Direction) -> (Observation, f32, bool) {
+        if self.done {
+            return (self.observe(), 0.0, true);
+        }
+        let score = self.game.score;
+        let mut reward = 0.0;
+        self.game.set_direction(action);
+        for _ in 0..self.ticks_per_step {
+            self.game.tick();
+            if self.game.munch_is_dead {
+                reward -= DEATH_PENALTY;
+                if !self.game.lose_life() {
+                    self.done = true;
+                    break;
+                }
+            } else if self.game.is_level_complete() {
+                self.game.next_level();
+            }
+        }
+        reward += (self.game.score - score) as f32;
+        (self.observe(), reward, self.done)
+    }
+
+    /// The game being played, e.g. for drawing it
+    pub fn game(&self) -> &game_logic::GameLogic {
+        &self.game
+    }
+
+    fn observe(&self) -> Observation {
+        let game = &self.game;
+        Observation {
+            width: game.maze.width,
+            height: game.maze.height,
+            tiles: game.maze.iter().copied().collect(),
+            munch: game.munch.get_draw_pos(),
+            ghosts: game
+                .ghosts
+                .iter()
+                .map(|g| GhostObservation {
+                    personality: g.personality,
+                    pos: g.actor.get_draw_pos(),
+                    mode: g.mode,
+                })
+                .collect(),
+            energised_time_left: game.energised_time_left(),
+            score: game.score,
+            lives: game.lives,
+            level: game.level,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_step_rewards_score_and_death() {
+        let maze_str = "
+#########
+#M..  B.#
+####R####
+#########
+";
+        let mut config = config::Config::from_string(maze_str).unwrap();
+        config.starting_lives = 1;
+        let mut env = Env::new(vec![config], 4);
+        let obs = env.reset(7);
+        pretty_assertions::assert_eq!(obs.tiles.len(), (obs.width * obs.height) as usize);
+        pretty_assertions::assert_eq!(obs.munch, (1.0, 1.0));
+        pretty_assertions::assert_eq!(obs.ghosts.len(), 1);
+        let mut total = 0.0;
+        let mut done = false;
+        for _ in 0..game_logic::TICK_RATE {
+            let (_, reward, step_done) = env.step(actor::Direction::Right);
+            total += reward;
+            done = step_done;
+            if done {
+                break;
+            }
+        }
+        // Munch eats both dots before running into the ghost
+        assert!(done);
+        pretty_assertions::assert_eq!(total, 20.0 - DEATH_PENALTY);
+        pretty_assertions::assert_eq!(env.step(actor::Direction::Left).1, 0.0);
+        pretty_assertions::assert_eq!(env.reset(7).score, 0);
+    }
+}
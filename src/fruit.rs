@@ -0,0 +1,58 @@
+use crate::{actor, maze};
+
+/// Number of dots eaten in a level after which each bonus fruit appears
+pub const SPAWN_DOTS: [i32; 2] = [70, 170];
+/// How long a bonus fruit stays before disappearing, in seconds
+const FRUIT_TIME: f32 = 9.5;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Kind {
+    Cherry,
+    Strawberry,
+    Orange,
+    Apple,
+    Melon,
+}
+
+impl Kind {
+    pub fn points(self) -> u32 {
+        match self {
+            Kind::Cherry => 100,
+            Kind::Strawberry => 300,
+            Kind::Orange => 500,
+            Kind::Apple => 700,
+            Kind::Melon => 1000,
+        }
+    }
+}
+
+/// A bonus fruit waiting to be eaten
+#[derive(Debug, Clone, PartialEq)]
+pub struct Fruit {
+    pub kind: Kind,
+    pub pos: (i32, i32),
+    /// Seconds left before the fruit disappears
+    pub timer: f32,
+}
+
+impl Fruit {
+    pub fn new(kind: Kind, pos: (i32, i32)) -> Self {
+        Fruit {
+            kind,
+            pos,
+            timer: FRUIT_TIME,
+        }
+    }
+
+    /// Return false once the fruit has run out of time
+    pub fn update(&mut self, time_delta: f32) -> bool {
+        self.timer -= time_delta;
+        self.timer > 0.0
+    }
+
+    pub fn is_eaten_by(&self, munch: &actor::Actor) -> bool {
+        munch
+            .get_covering_tiles(maze::EATING_THRESHOLD)
+            .contains(&self.pos)
+    }
+}
@@ -3,37 +3,105 @@ use ggez::event::EventHandler;
 use ggez::input::keyboard::{KeyCode, KeyInput};
 use ggez::{Context, GameResult};
 
-use crate::{audio, config, game_logic, window};
+use munch::{actor, bot, config, game_logic, replay, snapshot};
+
+use crate::{audio, window};
 
 const FRAME_TIME: f32 = 1000.0 / 120.0;
+/// Real time covered by a single simulation tick
+const TICK_DURATION: std::time::Duration =
+    std::time::Duration::from_nanos(1_000_000_000 / game_logic::TICK_RATE as u64);
+/// Cap on simulation time caught up in one frame, so a long stall doesn't
+/// cause a burst of ticks
+const MAX_FRAME_DURATION: std::time::Duration = std::time::Duration::from_millis(250);
+/// Where F5 saves the game and F9 loads it from, unless given on the command line
+pub const DEFAULT_SNAPSHOT_PATH: &str = "munch.snapshot";
+/// How long the maze flashes for between levels
+const LEVEL_COMPLETE_TIME: std::time::Duration = std::time::Duration::from_secs(2);
+
+#[derive(Clone, Debug, PartialEq)]
+enum State {
+    /// Title screen, waiting for the player to start a game
+    Attract,
+    /// Waiting for the beginning jingle to finish before play starts
+    Ready,
+    Playing,
+    /// Munch has been caught, waiting for the death animation to finish
+    Dying,
+    /// The maze has been cleared and is flashing before the next level
+    LevelComplete {
+        since: std::time::Instant,
+    },
+    GameOver,
+    /// The game is frozen, and will return to `previous` when resumed
+    Paused {
+        previous: Box<State>,
+    },
+}
+
+/// Options for a session, set from the command line
+pub struct Options {
+    /// Seed for the random choices of each game, unless a replay sets its own
+    pub seed: u64,
+    /// Save the inputs of each game to this file
+    pub record: Option<std::path::PathBuf>,
+    /// Play back a recorded game instead of taking input from the keyboard
+    pub replay: Option<replay::Replay>,
+    /// Resume this game instead of waiting on the attract screen
+    pub snapshot: Option<snapshot::Snapshot>,
+    /// File the game is saved to and loaded from with F5 and F9
+    pub snapshot_path: std::path::PathBuf,
+    /// Let the bot steer Munch instead of the keyboard
+    pub autoplay: bool,
+    /// Number of ticks simulated per tick of real time
+    pub fast_forward: u32,
+}
 
 pub struct Game {
     window: window::Window,
     audio: audio::Audio,
     spin_sleep: spin_sleep::SpinSleeper,
     last_game_update: std::time::Instant,
+    /// Real time not yet simulated, always less than one tick after an update
+    accumulator: std::time::Duration,
     game_logic: game_logic::GameLogic,
-    death_in_progress: bool,
-    startup_in_progress: bool,
+    state: State,
+    options: Options,
+    recording: replay::Replay,
+    playback: Option<replay::Playback>,
+    /// The game was loaded from a snapshot, so its recording can't be played back
+    resumed: bool,
 }
 
 impl Game {
-    pub fn new(ctx: &mut Context, config: config::Config) -> Game {
+    pub fn new(ctx: &mut Context, campaign: Vec<config::Config>, options: Options) -> Game {
         let window = window::Window::new(ctx);
-        let mut audio = audio::Audio::new(ctx);
-        let game_logic = game_logic::GameLogic::new(config);
+        let audio = audio::Audio::new(ctx);
+        let game_logic = game_logic::GameLogic::with_campaign(campaign);
         let spin_sleep = spin_sleep::SpinSleeper::new(100_000)
             .with_spin_strategy(spin_sleep::SpinStrategy::YieldThread);
-        audio.play_beginning(ctx);
-        Game {
+        let mut game = Game {
             window,
             audio,
             spin_sleep,
             last_game_update: std::time::Instant::now(),
+            accumulator: std::time::Duration::ZERO,
             game_logic,
-            death_in_progress: false,
-            startup_in_progress: true,
+            state: State::Attract,
+            options,
+            recording: replay::Replay::new(),
+            playback: None,
+            resumed: false,
+        };
+        if let Some(snapshot) = game.options.snapshot.take() {
+            game.resume(ctx, &snapshot);
+            return game;
         }
+        // Replays and autoplay start straight away, without waiting on the attract screen
+        if game.options.replay.is_some() || game.options.autoplay {
+            game.start_game(ctx);
+        }
+        game
     }
 
     fn sleep_frame(&mut self) {
@@ -42,64 +110,273 @@ impl Game {
         );
     }
 
-    fn get_time_delta(&self) -> f32 {
-        self.last_game_update.elapsed().as_millis() as f32 / 1000.0
+    /// Fraction of the way through the next tick, for interpolating actors when drawing
+    fn tick_alpha(&self) -> f32 {
+        if self.state == State::Playing && self.options.fast_forward <= 1 {
+            self.accumulator.as_secs_f32() / TICK_DURATION.as_secs_f32()
+        } else {
+            1.0
+        }
     }
 
-    fn update_death(&mut self, ctx: &mut Context) -> GameResult {
-        if self.death_in_progress && self.audio.death_is_finished() {
-            // self.death_in_progress = false;
-            std::process::exit(0);
+    /// Move to a new state, running any actions on entering it
+    fn set_state(&mut self, ctx: &mut Context, state: State) {
+        log::debug!("Game state {:?} -> {:?}", self.state, state);
+        self.accumulator = std::time::Duration::ZERO;
+        if matches!(self.state, State::Paused { .. }) {
+            self.audio.resume();
+        }
+        match state {
+            State::Attract | State::Paused { .. } => {}
+            State::Ready => self.audio.play_beginning(ctx),
+            State::Playing => self.audio.start_chomp(ctx),
+            State::Dying => {
+                log::info!("Munch is dead!");
+                self.window.reset_frame();
+                self.audio.play_death(ctx);
+            }
+            State::LevelComplete { .. } => {
+                log::info!("Level {} complete!", self.game_logic.level);
+                self.window.reset_frame();
+                self.audio.stop_chomp(ctx);
+            }
+            State::GameOver => {
+                log::info!("Game over!");
+                println!("Game over! Final score: {}", self.game_logic.score);
+                self.audio.stop_chomp(ctx);
+                self.save_recording();
+            }
         }
-        if self.death_in_progress {
-            return Ok(());
+        self.state = state;
+    }
+
+    fn start_game(&mut self, ctx: &mut Context) {
+        let seed = match &self.options.replay {
+            Some(replay) => replay.seed,
+            None => self.options.seed,
+        };
+        self.game_logic.set_seed(seed);
+        self.game_logic.restart();
+        self.recording = replay::Replay::new();
+        self.recording.seed = seed;
+        self.resumed = false;
+        self.playback = self.options.replay.clone().map(replay::Playback::new);
+        self.set_state(ctx, State::Ready);
+    }
+
+    /// Carry on playing from a snapshot
+    fn resume(&mut self, ctx: &mut Context, snapshot: &snapshot::Snapshot) {
+        match self.game_logic.restore(snapshot) {
+            Ok(()) => {
+                self.playback = None;
+                self.resumed = true;
+                self.set_state(ctx, State::Ready);
+            }
+            Err(e) => log::error!("Error loading snapshot: {}", e),
         }
-        log::info!("Munch is dead!");
-        println!("Munch is dead!");
-        self.death_in_progress = true;
-        self.window.reset_frame();
-        self.audio.play_death(ctx);
-        Ok(())
     }
 
-    fn update_startup(&mut self, ctx: &mut Context) -> GameResult {
-        if self.startup_in_progress && self.audio.beginning_is_finished() {
-            self.audio.start_chomp(ctx);
-            self.startup_in_progress = false;
+    fn save_snapshot(&self) {
+        if !matches!(
+            self.state,
+            State::Ready | State::Playing | State::Paused { .. }
+        ) {
+            return;
+        }
+        let path = &self.options.snapshot_path;
+        match self.game_logic.snapshot().save_to_file(path) {
+            Ok(()) => log::info!("Saved game to {}", path.display()),
+            Err(e) => log::error!("Error saving game: {}", e),
         }
-        Ok(())
+    }
+
+    fn load_snapshot(&mut self, ctx: &mut Context) {
+        match snapshot::Snapshot::from_file(&self.options.snapshot_path) {
+            Ok(snapshot) => self.resume(ctx, &snapshot),
+            Err(e) => log::error!("Error loading snapshot: {}", e),
+        }
+    }
+
+    fn save_recording(&self) {
+        if self.resumed && self.options.record.is_some() {
+            log::warn!("Not saving a replay of a game resumed from a snapshot");
+            return;
+        }
+        if let Some(path) = &self.options.record {
+            match self.recording.save_to_file(path) {
+                Ok(()) => log::info!("Saved replay to {}", path.display()),
+                Err(e) => log::error!("Error saving replay: {}", e),
+            }
+        }
+    }
+
+    /// Whether Munch is steered by the keyboard
+    fn has_player_control(&self) -> bool {
+        self.playback.is_none() && !self.options.autoplay
+    }
+
+    /// Advance the simulation by one tick, taking input from the replay if
+    /// one is playing or the bot if autoplaying, and recording it if requested
+    fn tick(&mut self) -> game_logic::ReturnState {
+        if let Some(playback) = &mut self.playback {
+            if playback.is_finished(&self.game_logic) {
+                log::info!("Replay finished, handing control to the player");
+                self.playback = None;
+            } else {
+                playback.apply(&mut self.game_logic);
+            }
+        }
+        if self.playback.is_none() && self.options.autoplay {
+            self.game_logic
+                .set_direction(bot::choose_direction(&self.game_logic));
+        }
+        self.recording.record(&self.game_logic);
+        let rs = self.game_logic.tick();
+        self.recording.length = self.game_logic.tick_count;
+        rs
+    }
+
+    /// Freeze the simulation and sound, if a game is under way
+    fn pause(&mut self) {
+        if matches!(self.state, State::Ready | State::Playing) {
+            let previous = Box::new(std::mem::replace(&mut self.state, State::Attract));
+            self.state = State::Paused { previous };
+            self.audio.pause();
+        }
+    }
+
+    fn toggle_pause(&mut self) {
+        match std::mem::replace(&mut self.state, State::Attract) {
+            State::Paused { previous } => {
+                self.state = *previous;
+                self.audio.resume();
+            }
+            state => {
+                self.state = state;
+                self.pause();
+            }
+        }
+    }
+
+    /// Advance exactly one tick while paused in play, to follow what the
+    /// ghosts decide tick by tick. Sounds are skipped.
+    fn step_tick(&mut self, ctx: &mut Context) {
+        if !matches!(&self.state, State::Paused { previous } if **previous == State::Playing) {
+            return;
+        }
+        self.tick();
+        log::debug!(
+            "Tick {}: Munch at {:?} heading {:?}",
+            self.game_logic.tick_count,
+            self.game_logic.munch.get_draw_pos(),
+            self.game_logic.munch.move_direction
+        );
+        for ghost in &self.game_logic.ghosts {
+            log::debug!(
+                "Tick {}: {:?} {:?} at {:?} heading {:?}",
+                self.game_logic.tick_count,
+                ghost.personality,
+                ghost.mode,
+                ghost.actor.get_draw_pos(),
+                ghost.actor.move_direction
+            );
+        }
+        self.handle_tick_outcome(ctx);
+    }
+
+    /// Leave play if Munch has died or cleared the maze.
+    /// Return whether play has ended.
+    fn handle_tick_outcome(&mut self, ctx: &mut Context) -> bool {
+        if self.game_logic.munch_is_dead {
+            self.set_state(ctx, State::Dying);
+        } else if self.game_logic.is_level_complete() {
+            let since = std::time::Instant::now();
+            self.set_state(ctx, State::LevelComplete { since });
+        } else {
+            return false;
+        }
+        true
+    }
+
+    fn update_playing(&mut self, ctx: &mut Context) {
+        let fast_forward = self.options.fast_forward.max(1);
+        self.accumulator += self.last_game_update.elapsed().min(MAX_FRAME_DURATION) * fast_forward;
+        while self.accumulator >= TICK_DURATION {
+            self.accumulator -= TICK_DURATION;
+            let rs = self.tick();
+            self.handle_audio_triggers(ctx, &rs);
+            if self.handle_tick_outcome(ctx) {
+                return;
+            }
+        }
+    }
+
+    fn update_dying(&mut self, ctx: &mut Context) {
+        if !self.audio.death_is_finished() {
+            return;
+        }
+        if self.game_logic.lose_life() {
+            self.set_state(ctx, State::Ready);
+        } else {
+            self.set_state(ctx, State::GameOver);
+        }
+    }
+
+    fn overlay_message(&self) -> Option<&'static str> {
+        match self.state {
+            State::Attract => Some("PRESS ENTER"),
+            State::Ready => Some("READY!"),
+            State::GameOver => Some("GAME OVER"),
+            State::Paused { .. } => Some("PAUSED"),
+            State::Playing | State::Dying | State::LevelComplete { .. } => None,
+        }
+    }
+
+    fn handle_direction_key(&mut self, keycode: KeyCode) {
+        let direction = match keycode {
+            KeyCode::Up => actor::Direction::Up,
+            KeyCode::Down => actor::Direction::Down,
+            KeyCode::Left => actor::Direction::Left,
+            KeyCode::Right => actor::Direction::Right,
+            _ => return,
+        };
+        self.game_logic.set_direction(direction);
     }
 
     fn handle_audio_triggers(&mut self, ctx: &mut Context, rs: &game_logic::ReturnState) {
         if rs.eaten_power_pellet {
             self.audio.play_power_pellet(ctx);
         }
-        if rs.eaten_ghost {
+        if rs.eaten_ghost.is_some() {
             self.audio.play_eat_ghost(ctx);
         }
+        if rs.extra_life {
+            self.audio.play_extra_life(ctx);
+        }
     }
 }
 
 impl EventHandler for Game {
     fn update(&mut self, ctx: &mut Context) -> GameResult {
         self.sleep_frame();
-        if self.game_logic.maze.n_dots == 0 {
-            log::info!("All dots eaten! You win!");
-            println!("All dots eaten! You win!");
-            std::process::exit(0);
-        }
-        let result = if self.startup_in_progress {
-            self.update_startup(ctx)
-        } else if self.game_logic.munch_is_dead {
-            self.update_death(ctx)
-        } else {
-            let time_delta = self.get_time_delta();
-            let rs = self.game_logic.update(time_delta);
-            self.handle_audio_triggers(ctx, &rs);
-            Ok(())
-        };
+        match self.state {
+            State::Attract | State::GameOver | State::Paused { .. } => {}
+            State::Ready => {
+                if self.audio.beginning_is_finished() {
+                    self.set_state(ctx, State::Playing);
+                }
+            }
+            State::Playing => self.update_playing(ctx),
+            State::Dying => self.update_dying(ctx),
+            State::LevelComplete { since } => {
+                if since.elapsed() >= LEVEL_COMPLETE_TIME {
+                    self.game_logic.next_level();
+                    self.set_state(ctx, State::Ready);
+                }
+            }
+        }
         self.last_game_update = std::time::Instant::now();
-        result
+        Ok(())
     }
 
     fn key_down_event(
@@ -112,24 +389,49 @@ impl EventHandler for Game {
             Some(key) => key,
             None => return Ok(()),
         };
-        self.game_logic.handle_movement(keycode);
+        if self.has_player_control() {
+            self.handle_direction_key(keycode);
+        }
         match keycode {
             KeyCode::Escape | KeyCode::Q => {
                 ctx.request_quit();
             }
+            KeyCode::Return | KeyCode::NumpadEnter
+                if matches!(self.state, State::Attract | State::GameOver) =>
+            {
+                self.start_game(ctx);
+            }
+            KeyCode::P => self.toggle_pause(),
+            KeyCode::Period => self.step_tick(ctx),
+            KeyCode::F5 => self.save_snapshot(),
+            KeyCode::F9 => self.load_snapshot(ctx),
             _ => {}
         }
         Ok(())
     }
 
+    fn focus_event(&mut self, _ctx: &mut Context, gained: bool) -> Result<(), GameError> {
+        if !gained {
+            self.pause();
+        }
+        Ok(())
+    }
+
+    fn quit_event(&mut self, _ctx: &mut Context) -> Result<bool, GameError> {
+        if self.state != State::GameOver {
+            self.save_recording();
+        }
+        Ok(false)
+    }
+
     fn draw(&mut self, ctx: &mut Context) -> GameResult {
         self.window.draw(
             ctx,
-            &self.game_logic.maze,
-            &self.game_logic.munch,
-            &self.game_logic.ghosts,
-            self.game_logic.score,
-            self.death_in_progress,
+            &self.game_logic,
+            self.tick_alpha(),
+            self.state == State::Dying,
+            matches!(self.state, State::LevelComplete { .. }),
+            self.overlay_message(),
         )
     }
 
@@ -1,14 +1,28 @@
-use ggez::input::keyboard::KeyCode;
+use crate::{actor, brain, config, fruit, ghost, ghost_house, level, maze, rng, snapshot};
 
-use crate::{actor, config, ghost, maze};
+/// Number of simulation ticks per second
+pub const TICK_RATE: u32 = 120;
+/// Time simulated by a single tick, in seconds
+pub const TICK_DELTA: f32 = 1.0 / TICK_RATE as f32;
 
-const ENERGISED_TIME: f32 = 10.0;
+/// Points for the first ghost eaten after a power pellet, doubling for each
+/// further ghost up to `MAX_GHOST_COMBO`
+const GHOST_POINTS: u32 = 200;
+const MAX_GHOST_COMBO: u32 = 3;
+/// How long the game freezes for after a ghost is eaten, in seconds
+const GHOST_EATEN_FREEZE: f32 = 0.5;
+/// How long a score popup stays on screen, in seconds
+const SCORE_POPUP_TIME: f32 = 1.0;
+/// Duration of each white flash of the frightened ghosts, plus the gap after it, in seconds
+const ENERGISED_FLASH_PERIOD: f32 = 0.4;
 
 /// Has Munch eaten a power pellet recently?
 /// If so, the ghosts can be eaten.
 struct Energised {
     is_energised: bool,
     timer: f32,
+    /// Ghosts eaten since the last power pellet
+    ghosts_eaten: u32,
 }
 
 impl Energised {
@@ -16,132 +30,595 @@ impl Energised {
         Energised {
             is_energised: false,
             timer: 0.0,
+            ghosts_eaten: 0,
         }
     }
 
+    /// Points for eating another ghost, which double with each ghost eaten
+    /// on the same power pellet
+    fn next_ghost_points(&mut self) -> u32 {
+        let points = GHOST_POINTS << self.ghosts_eaten.min(MAX_GHOST_COMBO);
+        self.ghosts_eaten += 1;
+        points
+    }
+
+    /// Return Some(true) if Munch has just been energised, and Some(false)
+    /// if he has just stopped being energised
     fn update(
         &mut self,
-        ghosts: &mut Vec<ghost::Ghost>,
         power_pellets_eaten: i32,
+        energised_time: f32,
         time_delta: f32,
-    ) {
+    ) -> Option<bool> {
         if power_pellets_eaten > 0 {
             log::info!("Munch is energised");
-            self.timer = ENERGISED_TIME;
+            self.timer = energised_time;
             self.is_energised = true;
-            for ghost in ghosts {
-                ghost.set_mode_scatter();
-            }
+            self.ghosts_eaten = 0;
+            return Some(true);
         } else if self.timer > 0.0 {
             self.timer -= time_delta;
         } else if self.is_energised {
             log::info!("Munch is no longer energised");
             self.is_energised = false;
-            for ghost in ghosts {
-                ghost.set_mode_chase();
-            }
+            return Some(false);
         }
+        None
     }
 }
 
+/// Alternates the ghosts between scattering to their corners and chasing
+/// Munch on a timer
+struct Schedule {
+    /// Phase durations, alternating starting with scatter
+    phases: Vec<f32>,
+    phase: usize,
+    timer: f32,
+}
+
+impl Schedule {
+    fn new(phases: Vec<f32>) -> Self {
+        let timer = phases.first().copied().unwrap_or(0.0);
+        Schedule {
+            phases,
+            phase: 0,
+            timer,
+        }
+    }
+
+    fn mode(&self) -> ghost::Mode {
+        if self.phase < self.phases.len() && self.phase.is_multiple_of(2) {
+            ghost::Mode::Scatter
+        } else {
+            ghost::Mode::Chase
+        }
+    }
+
+    /// Return true if the mode has changed
+    fn update(&mut self, time_delta: f32) -> bool {
+        if self.phase >= self.phases.len() {
+            return false;
+        }
+        self.timer -= time_delta;
+        if self.timer > 0.0 {
+            return false;
+        }
+        self.phase += 1;
+        self.timer += self.phases.get(self.phase).copied().unwrap_or(0.0);
+        log::info!("Ghosts switch to {:?}", self.mode());
+        true
+    }
+}
+
+/// Points awarded for eating a ghost or fruit, shown briefly where it was eaten
+#[derive(Clone, Debug, PartialEq)]
+pub struct ScorePopup {
+    pub points: u32,
+    pub pos: (i32, i32),
+    timer: f32,
+}
+
 pub struct ReturnState {
     pub eaten_power_pellet: bool,
-    pub eaten_ghost: bool,
+    /// Points awarded if any ghosts were eaten
+    pub eaten_ghost: Option<u32>,
+    /// Points awarded if a bonus fruit was eaten
+    pub eaten_fruit: Option<u32>,
+    /// Munch earned an extra life
+    pub extra_life: bool,
 }
 
 pub struct GameLogic {
+    /// Mazes played in order, repeating from the start after the last one
+    campaign: Vec<config::Config>,
     pub maze: maze::Maze,
     pub munch: actor::Actor,
     pub ghosts: Vec<ghost::Ghost>,
     move_direction: actor::Direction,
     energised: Energised,
+    schedule: Schedule,
+    ghost_house: ghost_house::GhostHouse,
     pub score: u32,
+    pub lives: u32,
+    /// Score at which the next extra life is awarded, if any
+    next_extra_life: Option<u32>,
+    /// Points between repeated extra lives, if they repeat. This, the first
+    /// threshold and the starting lives are taken from the first maze of the
+    /// campaign and kept for the whole game.
+    extra_life_every: Option<u32>,
+    /// An extra life has been awarded since the last update was reported
+    extra_life_awarded: bool,
+    pub level: u32,
     pub munch_is_dead: bool,
+    pub popups: Vec<ScorePopup>,
+    pub fruit: Option<fruit::Fruit>,
+    /// Number of fruits which have appeared so far this level
+    fruits_spawned: usize,
+    /// Every fruit eaten this game, oldest first
+    pub fruit_history: Vec<fruit::Kind>,
+    /// Time left frozen after eating a ghost, in seconds
+    freeze_timer: f32,
+    /// Number of fixed ticks simulated so far
+    pub tick_count: u64,
+    /// Tick at which Munch and the ghosts were last put back at their spawn positions
+    respawn_tick: u64,
+    /// Seed the game was started with
+    seed: u64,
+    /// Source of every random choice in the game
+    rng: rng::Rng,
+}
+
+fn spawn_munch(config: &config::Config) -> actor::Actor {
+    let (x, y) = config.player_pos;
+    actor::Actor::new(x, y)
+}
+
+fn spawn_ghosts(config: &config::Config, level: u32) -> Vec<ghost::Ghost> {
+    let params = level::level_params(level);
+    config
+        .ghosts_pos
+        .iter()
+        .map(|&(x, y, personality)| {
+            let mut ghost = ghost::Ghost::new(x, y, personality);
+            ghost.speeds = params.speeds;
+            ghost.brain = brain::for_personality(personality, level);
+            ghost
+        })
+        .collect()
 }
 
 impl GameLogic {
     pub fn new(config: config::Config) -> GameLogic {
-        let munch = match config.player_pos {
-            Some((x, y)) => actor::Actor::new(x, y),
-            None => actor::Actor::new(0, 0),
-        };
-        GameLogic {
-            maze: config.maze,
-            munch,
-            ghosts: config
-                .ghosts_pos
-                .iter()
-                .map(|&(x, y, personality)| ghost::Ghost::new(x, y, personality))
-                .collect(),
+        Self::with_campaign(vec![config])
+    }
+
+    /// Create a game which moves on to the next maze of `campaign` each level
+    pub fn with_campaign(campaign: Vec<config::Config>) -> GameLogic {
+        assert!(!campaign.is_empty(), "Campaign must contain a maze");
+        let config = &campaign[0];
+        let mut game = GameLogic {
+            maze: config.maze.clone(),
+            munch: spawn_munch(config),
+            ghosts: Vec::new(),
             move_direction: actor::Direction::Still,
             energised: Energised::new(),
+            schedule: Schedule::new(Vec::new()),
+            ghost_house: ghost_house::GhostHouse::new(),
             score: 0,
+            lives: config.starting_lives,
+            next_extra_life: config.extra_life,
+            extra_life_every: config.extra_life_every.filter(|&every| every > 0),
+            extra_life_awarded: false,
+            level: 1,
             munch_is_dead: false,
-        }
+            popups: Vec::new(),
+            fruit: None,
+            fruits_spawned: 0,
+            fruit_history: Vec::new(),
+            freeze_timer: 0.0,
+            tick_count: 0,
+            respawn_tick: 0,
+            seed: 0,
+            rng: rng::Rng::new(0),
+            campaign,
+        };
+        game.reset_actors();
+        game
+    }
+
+    /// The config of the maze for the current level
+    fn config(&self) -> &config::Config {
+        &self.campaign[(self.level as usize - 1) % self.campaign.len()]
     }
 
-    pub fn handle_movement(&mut self, keycode: KeyCode) {
-        match keycode {
-            KeyCode::Up => self.move_direction = actor::Direction::Up,
-            KeyCode::Down => self.move_direction = actor::Direction::Down,
-            KeyCode::Left => self.move_direction = actor::Direction::Left,
-            KeyCode::Right => self.move_direction = actor::Direction::Right,
-            _ => {}
+    /// Start a new game from level 1 with the original maze, a full set of
+    /// lives and the same seed
+    pub fn restart(&mut self) {
+        let seed = self.seed;
+        *self = GameLogic::with_campaign(self.campaign.clone());
+        self.set_seed(seed);
+    }
+
+    /// Reseed the random choices of the game. The same seed and inputs from
+    /// the start of a game always play out the same way.
+    pub fn set_seed(&mut self, seed: u64) {
+        self.seed = seed;
+        self.rng = rng::Rng::new(seed);
+    }
+
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    /// Capture the game at this moment, to resume later with `restore`
+    pub fn snapshot(&self) -> snapshot::Snapshot {
+        let actor_state = |actor: &actor::Actor| snapshot::ActorState {
+            x: actor.x,
+            y: actor.y,
+            progress: actor.progress_to_next_square,
+            direction: actor.move_direction,
+        };
+        snapshot::Snapshot {
+            level: self.level,
+            score: self.score,
+            lives: self.lives,
+            tick_count: self.tick_count,
+            seed: self.seed,
+            rng_state: self.rng.state(),
+            direction: self.move_direction,
+            next_extra_life: self.next_extra_life,
+            is_energised: self.energised.is_energised,
+            energised_timer: self.energised.timer,
+            ghosts_eaten: self.energised.ghosts_eaten,
+            schedule_phase: self.schedule.phase,
+            schedule_timer: self.schedule.timer,
+            ghost_house: self.ghost_house.clone(),
+            freeze_timer: self.freeze_timer,
+            fruits_spawned: self.fruits_spawned,
+            fruit_history: self.fruit_history.clone(),
+            fruit: self.fruit.as_ref().map(|f| (f.kind, f.timer)),
+            munch: actor_state(&self.munch),
+            ghosts: self
+                .ghosts
+                .iter()
+                .map(|g| snapshot::GhostState {
+                    personality: g.personality,
+                    mode: g.mode,
+                    actor: actor_state(&g.actor),
+                })
+                .collect(),
+            maze: self.maze.to_string().lines().map(String::from).collect(),
         }
     }
 
-    /// If there is a collision between the munch and a ghost,
-    /// return the index of the ghost. A collision is considered to have
-    /// occurred if the positions of the munch and the ghost are the same.
-    fn munch_ghost_collision(&self) -> Option<usize> {
-        for (i, ghost) in self.ghosts.iter().enumerate() {
-            if self.munch.get_pos() == ghost.actor.get_pos() {
-                return Some(i);
+    /// Resume a game from a snapshot taken while playing the same campaign.
+    /// The game is left as it was if the snapshot doesn't fit the campaign.
+    pub fn restore(&mut self, snapshot: &snapshot::Snapshot) -> Result<(), String> {
+        if snapshot.level == 0 {
+            return Err("Level must be at least 1".to_string());
+        }
+        let mut game = GameLogic::with_campaign(self.campaign.clone());
+        game.level = snapshot.level;
+        game.maze = game.config().maze.clone();
+        game.reset_actors();
+        let mismatch = || format!("Maze doesn't match the maze for level {}", snapshot.level);
+        let expected = game.maze.to_string();
+        if expected.lines().count() != snapshot.maze.len() {
+            return Err(mismatch());
+        }
+        for (y, (expected, row)) in expected.lines().zip(&snapshot.maze).enumerate() {
+            if expected.chars().count() != row.chars().count() {
+                return Err(mismatch());
+            }
+            for (x, (expected, c)) in expected.chars().zip(row.chars()).enumerate() {
+                match (expected, c) {
+                    _ if expected == c => {}
+                    // Eaten dots and power pellets
+                    ('.' | '*', ' ') => game.maze.set_tile(x as i32, y as i32, maze::Tile::Path),
+                    _ => return Err(format!("{} at ({}, {})", mismatch(), x, y)),
+                }
             }
         }
-        None
+        let restore_actor = |actor: &mut actor::Actor, state: &snapshot::ActorState| {
+            actor.x = state.x;
+            actor.y = state.y;
+            actor.progress_to_next_square = state.progress;
+            actor.move_direction = state.direction;
+            actor.save_draw_pos();
+        };
+        if game.ghosts.len() != snapshot.ghosts.len() {
+            return Err(format!(
+                "Expected {} ghosts for level {}, found {}",
+                game.ghosts.len(),
+                snapshot.level,
+                snapshot.ghosts.len()
+            ));
+        }
+        for (ghost, state) in game.ghosts.iter_mut().zip(&snapshot.ghosts) {
+            if ghost.personality != state.personality {
+                return Err(format!(
+                    "Expected {:?} for level {}, found {:?}",
+                    ghost.personality, snapshot.level, state.personality
+                ));
+            }
+            ghost.mode = state.mode;
+            restore_actor(&mut ghost.actor, &state.actor);
+        }
+        restore_actor(&mut game.munch, &snapshot.munch);
+        game.fruit = match (snapshot.fruit, game.config().fruit_pos) {
+            (Some((kind, timer)), Some(pos)) => Some(fruit::Fruit { kind, pos, timer }),
+            (Some(_), None) => {
+                return Err(format!("Level {} has no bonus fruit", snapshot.level));
+            }
+            (None, _) => None,
+        };
+        game.score = snapshot.score;
+        game.lives = snapshot.lives;
+        game.tick_count = snapshot.tick_count;
+        game.seed = snapshot.seed;
+        game.rng = rng::Rng::new(snapshot.rng_state);
+        game.move_direction = snapshot.direction;
+        game.next_extra_life = snapshot.next_extra_life;
+        game.energised.is_energised = snapshot.is_energised;
+        game.energised.timer = snapshot.energised_timer;
+        game.energised.ghosts_eaten = snapshot.ghosts_eaten;
+        game.schedule.phase = snapshot.schedule_phase;
+        game.schedule.timer = snapshot.schedule_timer;
+        game.ghost_house = snapshot.ghost_house.clone();
+        game.freeze_timer = snapshot.freeze_timer;
+        game.fruits_spawned = snapshot.fruits_spawned;
+        game.fruit_history = snapshot.fruit_history.clone();
+        *self = game;
+        Ok(())
     }
 
-    fn get_blinky_pos(&self) -> (i32, i32) {
-        self.ghosts
-            .iter()
-            .find(|g| matches!(g.personality, ghost::Personality::Blinky))
-            .map(|g| g.actor.get_pos())
-            .unwrap_or((0, 0))
+    /// Lose a life after Munch has died, putting Munch and the ghosts back at
+    /// their spawn positions. The maze and score are left as they are.
+    /// Return false if there are no lives remaining, i.e. the game is over.
+    pub fn lose_life(&mut self) -> bool {
+        self.lives = self.lives.saturating_sub(1);
+        if self.is_game_over() {
+            log::info!("Munch has run out of lives");
+            return false;
+        }
+        log::info!("Munch has {} lives remaining", self.lives);
+        self.reset_actors();
+        true
+    }
+
+    pub fn is_game_over(&self) -> bool {
+        self.lives == 0
+    }
+
+    /// Seconds until Munch stops being energised, or zero if not energised
+    pub fn energised_time_left(&self) -> f32 {
+        if self.energised.is_energised {
+            self.energised.timer.max(0.0)
+        } else {
+            0.0
+        }
+    }
+
+    /// Should frightened ghosts be drawn white, to warn that Munch is about
+    /// to stop being energised?
+    pub fn frightened_ghosts_flash(&self) -> bool {
+        let time_left = self.energised_time_left();
+        let flashes = level::level_params(self.level).energised_flashes;
+        time_left > 0.0
+            && time_left <= flashes as f32 * ENERGISED_FLASH_PERIOD
+            && (time_left / ENERGISED_FLASH_PERIOD).fract() >= 0.5
+    }
+
+    /// Is the game paused for a moment after Munch ate a ghost?
+    pub fn is_frozen(&self) -> bool {
+        self.freeze_timer > 0.0
+    }
+
+    pub fn is_level_complete(&self) -> bool {
+        self.maze.n_dots == 0
+    }
+
+    /// Advance to the next level, loading the next maze of the campaign.
+    /// The score and lives carry over.
+    pub fn next_level(&mut self) {
+        self.level += 1;
+        log::info!("Starting level {}", self.level);
+        self.maze = self.config().maze.clone();
+        self.fruits_spawned = 0;
+        self.reset_actors();
+    }
+
+    fn reset_actors(&mut self) {
+        let phases = match &self.config().schedule {
+            Some(phases) => phases.clone(),
+            None => level::level_params(self.level).schedule.to_vec(),
+        };
+        self.schedule = Schedule::new(phases);
+        self.munch = spawn_munch(self.config());
+        self.ghosts = spawn_ghosts(self.config(), self.level);
+        for ghost in &mut self.ghosts {
+            // Ghosts starting in the pen wait there until they are released
+            let (x, y) = ghost.actor.get_pos();
+            ghost.mode = if self.maze.house_exit.is_some()
+                && self.maze.tile(x, y) == maze::Tile::PlayerImpassable
+            {
+                ghost::Mode::InHouse
+            } else {
+                self.schedule.mode()
+            };
+        }
+        self.ghost_house = ghost_house::GhostHouse::new();
+        self.move_direction = actor::Direction::Still;
+        self.respawn_tick = self.tick_count;
+        self.energised = Energised::new();
+        self.munch_is_dead = false;
+        self.popups.clear();
+        self.fruit = None;
+        self.freeze_timer = 0.0;
+    }
+
+    /// Set the direction Munch will try to move in from the next update
+    pub fn set_direction(&mut self, direction: actor::Direction) {
+        self.move_direction = direction;
     }
 
-    fn add_score(&mut self, dots_eaten: i32, power_pellets_eaten: i32) {
-        self.score += dots_eaten as u32 * 10 + power_pellets_eaten as u32 * 50;
+    pub fn direction(&self) -> actor::Direction {
+        self.move_direction
+    }
+
+    /// Tick at which Munch last respawned, after a death or a new level.
+    /// Munch is left standing still, whichever way he was being steered before.
+    pub fn respawn_tick(&self) -> u64 {
+        self.respawn_tick
+    }
+
+    /// Return the indices of every ghost Munch has collided with. A collision
+    /// is considered to have occurred if they came within the collision radius
+    /// of each other at any point during the last update.
+    fn munch_ghost_collisions(&self) -> Vec<usize> {
+        let radius = self.config().collision_radius;
+        (0..self.ghosts.len())
+            .filter(|&i| {
+                self.munch
+                    .closest_approach(&self.ghosts[i].actor, &self.maze)
+                    < radius
+            })
+            .collect()
+    }
+
+    /// Munch slows down while moving onto a dot or power pellet
+    fn munch_speed(&self) -> f32 {
+        let speeds = level::level_params(self.level).speeds;
+        let (x, y) = self.munch.tile_ahead();
+        match self.maze.tile(x, y) {
+            maze::Tile::Dot | maze::Tile::PowerPellet => speeds.munch_eating,
+            _ => speeds.munch,
+        }
+    }
+
+    /// Add to the score, awarding an extra life each time it passes the next threshold
+    fn add_score(&mut self, points: u32) {
+        self.score += points;
+        while let Some(threshold) = self.next_extra_life.filter(|&t| self.score >= t) {
+            self.lives += 1;
+            self.extra_life_awarded = true;
+            log::info!("Extra life at {} points", threshold);
+            self.next_extra_life = self.extra_life_every.map(|every| threshold + every);
+        }
     }
 
     fn handle_eating(&mut self, time_delta: f32) -> bool {
         let dots_eaten = self.maze.eat_dots(&self.munch);
+        self.handle_ghost_house(dots_eaten, time_delta);
         let power_pellets_eaten = self.maze.eat_power_pellets(&self.munch);
-        self.energised
-            .update(&mut self.ghosts, power_pellets_eaten, time_delta);
-        self.add_score(dots_eaten, power_pellets_eaten);
+        let energised_time = level::level_params(self.level).energised_time;
+        match self
+            .energised
+            .update(power_pellets_eaten, energised_time, time_delta)
+        {
+            Some(true) => {
+                let positions = self.ghost_positions();
+                let world = brain::WorldView {
+                    maze: &self.maze,
+                    munch: &self.munch,
+                    ghosts: &positions,
+                };
+                for ghost in &mut self.ghosts {
+                    ghost.set_mode_frightened(&world, &mut self.rng);
+                }
+            }
+            Some(false) => {
+                for ghost in &mut self.ghosts {
+                    ghost.end_frightened(self.schedule.mode());
+                }
+            }
+            None => {}
+        }
+        self.add_score(dots_eaten as u32 * 10 + power_pellets_eaten as u32 * 50);
         power_pellets_eaten > 0
     }
 
+    /// Advance the scatter/chase schedule, which is paused while Munch is energised
+    fn handle_schedule(&mut self, time_delta: f32) {
+        if self.energised.is_energised || !self.schedule.update(time_delta) {
+            return;
+        }
+        let mode = self.schedule.mode();
+        let positions = self.ghost_positions();
+        let world = brain::WorldView {
+            maze: &self.maze,
+            munch: &self.munch,
+            ghosts: &positions,
+        };
+        for ghost in &mut self.ghosts {
+            ghost.set_scheduled_mode(mode, &world, &mut self.rng);
+        }
+    }
+
+    /// Let the next ghost out of the ghost house when it is due
+    fn handle_ghost_house(&mut self, dots_eaten: i32, time_delta: f32) {
+        let params = level::level_params(self.level);
+        if let Some(index) = self
+            .ghost_house
+            .update(&self.ghosts, dots_eaten, &params, time_delta)
+        {
+            log::info!(
+                "{:?} is leaving the ghost house",
+                self.ghosts[index].personality
+            );
+            self.ghosts[index].leave_house(&self.maze, self.schedule.mode());
+        }
+    }
+
+    /// Where each ghost is, for the `WorldView` the ghosts decide from
+    fn ghost_positions(&self) -> Vec<(ghost::Personality, (i32, i32))> {
+        self.ghosts
+            .iter()
+            .map(|g| (g.personality, g.actor.get_pos()))
+            .collect()
+    }
+
     fn handle_ghost_movement(&mut self, time_delta: f32) {
-        let blinky_pos = self.get_blinky_pos();
+        let positions = self.ghost_positions();
+        let world = brain::WorldView {
+            maze: &self.maze,
+            munch: &self.munch,
+            ghosts: &positions,
+        };
         for ghost in &mut self.ghosts {
-            ghost.move_along_path(&self.maze, &self.munch, blinky_pos, time_delta);
+            ghost.move_along_path(&world, &mut self.rng, time_delta);
             if ghost.actor.get_pos() == self.maze.respawn_point && ghost.mode == ghost::Mode::Eaten
             {
                 log::info!("{:?} has respawned", ghost.personality);
-                ghost.mode = ghost::Mode::Chase;
+                ghost.leave_house(&self.maze, self.schedule.mode());
             }
+            ghost.update_leaving_house(&self.maze, self.schedule.mode());
         }
     }
 
-    fn handle_ghost_collision(&mut self, ghost_index: usize) -> bool {
-        if self.energised.is_energised {
-            if self.ghosts[ghost_index].eat_ghost() {
-                log::info!("Munch has eaten {:?}", self.ghosts[ghost_index].personality);
-                self.score += 200;
-                return true;
-            }
+    /// Handle every ghost Munch has collided with, so that a dangerous ghost
+    /// kills him even if he also ran into eyes or a frightened ghost.
+    /// Return the points awarded for any ghosts eaten.
+    fn handle_ghost_collisions(&mut self) -> Option<u32> {
+        self.munch_ghost_collisions()
+            .into_iter()
+            .filter_map(|index| self.handle_ghost_collision(index))
+            .reduce(|a, b| a + b)
+    }
+
+    /// Return the points awarded if Munch ate the ghost
+    fn handle_ghost_collision(&mut self, ghost_index: usize) -> Option<u32> {
+        if self.ghosts[ghost_index].eat_ghost() {
+            let points = self.energised.next_ghost_points();
+            log::info!(
+                "Munch has eaten {:?} for {} points",
+                self.ghosts[ghost_index].personality,
+                points
+            );
+            self.add_score(points);
+            self.add_popup(points, self.ghosts[ghost_index].actor.get_pos());
+            self.freeze_timer = GHOST_EATEN_FREEZE;
+            return Some(points);
         } else if self.ghosts[ghost_index].mode != ghost::Mode::Eaten {
             log::info!(
                 "Munch collided with {:?}.",
@@ -149,20 +626,86 @@ impl GameLogic {
             );
             self.munch_is_dead = true;
         }
-        false
+        None
+    }
+
+    /// Put out a bonus fruit once enough dots have been eaten, and return the
+    /// points awarded if Munch eats it before it disappears
+    fn handle_fruit(&mut self, time_delta: f32) -> Option<u32> {
+        let pos = self.config().fruit_pos?;
+        let dots_eaten = self.config().maze.n_dots - self.maze.n_dots;
+        if fruit::SPAWN_DOTS
+            .get(self.fruits_spawned)
+            .is_some_and(|&dots| dots_eaten >= dots)
+        {
+            self.fruits_spawned += 1;
+            let kind = level::level_params(self.level).fruit;
+            log::info!("{:?} has appeared", kind);
+            self.fruit = Some(fruit::Fruit::new(kind, pos));
+        }
+        let fruit = self.fruit.as_mut()?;
+        if fruit.is_eaten_by(&self.munch) {
+            let kind = fruit.kind;
+            log::info!("Munch has eaten {:?}", kind);
+            self.fruit = None;
+            self.fruit_history.push(kind);
+            self.add_score(kind.points());
+            self.add_popup(kind.points(), pos);
+            return Some(kind.points());
+        }
+        if !fruit.update(time_delta) {
+            log::info!("{:?} has disappeared", fruit.kind);
+            self.fruit = None;
+        }
+        None
+    }
+
+    fn add_popup(&mut self, points: u32, pos: (i32, i32)) {
+        self.popups.push(ScorePopup {
+            points,
+            pos,
+            timer: SCORE_POPUP_TIME,
+        });
+    }
+
+    fn handle_popups(&mut self, time_delta: f32) {
+        for popup in &mut self.popups {
+            popup.timer -= time_delta;
+        }
+        self.popups.retain(|popup| popup.timer > 0.0);
+    }
+
+    /// Advance the simulation by a single fixed tick of `TICK_DELTA` seconds.
+    /// Given the same seed and sequence of directions, this is fully reproducible.
+    pub fn tick(&mut self) -> ReturnState {
+        self.tick_count += 1;
+        self.update(TICK_DELTA)
     }
 
     pub fn update(&mut self, time_delta: f32) -> ReturnState {
         let mut rs = ReturnState {
             eaten_power_pellet: false,
-            eaten_ghost: false,
+            eaten_ghost: None,
+            eaten_fruit: None,
+            extra_life: false,
         };
+        self.munch.save_draw_pos();
+        for ghost in &mut self.ghosts {
+            ghost.actor.save_draw_pos();
+        }
+        self.handle_popups(time_delta);
+        if self.is_frozen() {
+            self.freeze_timer -= time_delta;
+            return rs;
+        }
+        self.munch.speed = self.munch_speed();
         self.munch.walk(self.move_direction, &self.maze, time_delta);
+        self.handle_schedule(time_delta);
         self.handle_ghost_movement(time_delta);
-        if let Some(index) = self.munch_ghost_collision() {
-            rs.eaten_ghost = self.handle_ghost_collision(index);
-        }
+        rs.eaten_ghost = self.handle_ghost_collisions();
         rs.eaten_power_pellet = self.handle_eating(time_delta);
+        rs.eaten_fruit = self.handle_fruit(time_delta);
+        rs.extra_life = std::mem::take(&mut self.extra_life_awarded);
         rs
     }
 }
@@ -173,10 +716,406 @@ mod tests {
 
     #[test]
     fn test_munch_ghost_collisions() {
+        let maze_str = "
+#########
+#M.....B#
+####R####
+#########
+";
+        let mut game = GameLogic::new(config::Config::from_string(maze_str).unwrap());
+        game.ghosts[0].actor.set_pos(1, 1);
+        assert_eq!(game.munch_ghost_collisions(), vec![0]);
+        game.ghosts[0].actor.set_pos(2, 1);
+        assert_eq!(game.munch_ghost_collisions(), Vec::<usize>::new());
+    }
+
+    #[test]
+    fn test_collision_with_overlapping_ghosts() {
+        let maze_str = "
+#########
+#M....PB#
+####R####
+#########
+";
+        let mut game = GameLogic::new(config::Config::from_string(maze_str).unwrap());
+        for ghost in &mut game.ghosts {
+            ghost.actor.set_pos(1, 1);
+        }
+        // Eyes heading home don't stop the live ghost behind them killing Munch
+        game.ghosts[0].mode = ghost::Mode::Eaten;
+        game.ghosts[1].mode = ghost::Mode::Chase;
+        pretty_assertions::assert_eq!(game.handle_ghost_collisions(), None);
+        assert!(game.munch_is_dead);
+
+        // Both frightened ghosts are eaten
+        game.munch_is_dead = false;
+        game.energised.is_energised = true;
+        for ghost in &mut game.ghosts {
+            ghost.mode = ghost::Mode::Frightened;
+        }
+        pretty_assertions::assert_eq!(game.handle_ghost_collisions(), Some(200 + 400));
+        assert!(!game.munch_is_dead);
+    }
+
+    #[test]
+    fn test_head_on_collision_at_any_speed() {
+        let maze_str = "
+#######
+#M  B #
+#R#####
+#.#####
+#######
+";
+        // Large enough time deltas make Munch and the ghost swap tiles in a
+        // single update, without ever being on the same tile
+        for (time_delta, ghost_speed) in [(TICK_DELTA, 3.0), (0.25, 1.0), (0.5, 1.0), (0.25, 3.0)] {
+            let mut game = GameLogic::new(config::Config::from_string(maze_str).unwrap());
+            game.ghosts[0].speeds.ghost = ghost_speed;
+            game.ghosts[0].actor.move_direction = actor::Direction::Left;
+            game.set_direction(actor::Direction::Right);
+            for _ in 0..(1.0 / time_delta) as usize {
+                game.update(time_delta);
+                if game.munch_is_dead {
+                    break;
+                }
+            }
+            assert!(
+                game.munch_is_dead,
+                "no collision with time delta {}",
+                time_delta
+            );
+            // The ghost is caught before it has got past Munch
+            let (munch_x, _) = game.munch.get_draw_pos();
+            let (ghost_x, _) = game.ghosts[0].actor.get_draw_pos();
+            assert!(
+                munch_x <= ghost_x + 1.0,
+                "passed through with time delta {}",
+                time_delta
+            );
+        }
+    }
+
+    #[test]
+    fn test_headless_game_eats_dots() {
+        let maze_str = "
+#######
+#M... #
+###R###
+#######
+";
+        let mut game = GameLogic::new(config::Config::from_string(maze_str).unwrap());
+        game.set_direction(actor::Direction::Right);
+        for _ in 0..20 {
+            game.update(0.1);
+        }
+        pretty_assertions::assert_eq!(game.munch.get_pos(), (5, 1));
+        pretty_assertions::assert_eq!(game.score, 30);
+        assert!(game.is_level_complete());
+    }
+
+    #[test]
+    fn test_ticks_are_reproducible() {
+        let run = || {
+            let mut game = GameLogic::new(
+                config::Config::from_string(include_str!("../resources/maze.txt")).unwrap(),
+            );
+            let directions = [
+                actor::Direction::Left,
+                actor::Direction::Up,
+                actor::Direction::Right,
+                actor::Direction::Down,
+            ];
+            for i in 0..2000 {
+                if i % 150 == 0 {
+                    game.set_direction(directions[(i / 150) % directions.len()]);
+                }
+                game.tick();
+                if game.munch_is_dead {
+                    break;
+                }
+            }
+            let ghosts = game
+                .ghosts
+                .iter()
+                .map(|g| g.actor.get_draw_pos())
+                .collect::<Vec<_>>();
+            (
+                game.tick_count,
+                game.score,
+                game.munch.get_draw_pos(),
+                ghosts,
+            )
+        };
+        pretty_assertions::assert_eq!(run(), run());
+    }
+
+    #[test]
+    fn test_only_frightened_ghosts_are_eaten() {
         let mut game = GameLogic::new(config::Config::empty());
         game.munch.set_pos(5, 5);
         game.ghosts
             .push(ghost::Ghost::new(5, 5, ghost::Personality::Blinky));
-        assert_eq!(game.munch_ghost_collision(), Some(0));
+        game.ghosts[0].mode = ghost::Mode::Frightened;
+        assert!(game.handle_ghost_collision(0).is_some());
+        pretty_assertions::assert_eq!(game.ghosts[0].mode, ghost::Mode::Eaten);
+        assert!(!game.munch_is_dead);
+        // A ghost which has respawned is dangerous again, even if Munch is still energised
+        game.energised.is_energised = true;
+        game.ghosts[0].mode = ghost::Mode::Chase;
+        assert!(game.handle_ghost_collision(0).is_none());
+        assert!(game.munch_is_dead);
+    }
+
+    #[test]
+    fn test_ghost_points_combo() {
+        let maze_str = "
+#######
+#M.   #
+###R###
+#######
+";
+        let mut game = GameLogic::new(config::Config::from_string(maze_str).unwrap());
+        for personality in [
+            ghost::Personality::Blinky,
+            ghost::Personality::Pinky,
+            ghost::Personality::Inky,
+            ghost::Personality::Clyde,
+        ] {
+            game.ghosts.push(ghost::Ghost::new(1, 1, personality));
+        }
+        let eat_all = |game: &mut GameLogic| {
+            (0..game.ghosts.len())
+                .map(|i| {
+                    game.ghosts[i].mode = ghost::Mode::Frightened;
+                    game.handle_ghost_collision(i).unwrap()
+                })
+                .collect::<Vec<_>>()
+        };
+        pretty_assertions::assert_eq!(eat_all(&mut game), vec![200, 400, 800, 1600]);
+        pretty_assertions::assert_eq!(game.score, 3000);
+        pretty_assertions::assert_eq!(game.popups.len(), 4);
+        assert!(game.is_frozen());
+        // A new power pellet starts the combo again
+        game.energised.update(1, 1.0, 0.0);
+        pretty_assertions::assert_eq!(eat_all(&mut game), vec![200, 400, 800, 1600]);
+        // Nothing moves during the freeze, and the popups disappear afterwards
+        let pos = game.munch.get_draw_pos();
+        game.set_direction(actor::Direction::Right);
+        game.update(GHOST_EATEN_FREEZE);
+        pretty_assertions::assert_eq!(game.munch.get_draw_pos(), pos);
+        game.update(SCORE_POPUP_TIME);
+        assert!(game.popups.is_empty());
+    }
+
+    #[test]
+    fn test_fruit_appears_after_dots_and_expires() {
+        let maze_str = "
+##########
+#M.F.    #
+####R#####
+##########
+";
+        let mut config = config::Config::from_string(maze_str).unwrap();
+        pretty_assertions::assert_eq!(config.fruit_pos, Some((3, 1)));
+        // Pad the maze with enough uneaten dots to reach the fruit thresholds
+        config.maze.n_dots += fruit::SPAWN_DOTS[1];
+        let mut game = GameLogic::new(config);
+        game.maze.n_dots -= fruit::SPAWN_DOTS[0] - 1;
+        game.update(0.0);
+        pretty_assertions::assert_eq!(game.fruit, None);
+        game.set_direction(actor::Direction::Right);
+        for _ in 0..3 {
+            game.update(0.1);
+        }
+        let fruit = game.fruit.clone().unwrap();
+        pretty_assertions::assert_eq!(fruit.kind, fruit::Kind::Cherry);
+        for _ in 0..5 {
+            game.update(0.1);
+        }
+        pretty_assertions::assert_eq!(game.fruit, None);
+        pretty_assertions::assert_eq!(game.fruit_history, vec![fruit::Kind::Cherry]);
+        pretty_assertions::assert_eq!(game.score, 2 * 10 + 100);
+        // The second fruit disappears if it isn't eaten in time
+        game.munch.set_pos(6, 1);
+        game.set_direction(actor::Direction::Still);
+        game.maze.n_dots = game.config().maze.n_dots - fruit::SPAWN_DOTS[1];
+        game.update(0.0);
+        assert!(game.fruit.is_some());
+        game.update(10.0);
+        pretty_assertions::assert_eq!(game.fruit, None);
+    }
+
+    #[test]
+    fn test_extra_life_at_score_thresholds() {
+        let mut config = config::Config::empty();
+        config.extra_life = Some(1000);
+        let mut game = GameLogic::new(config.clone());
+        game.add_score(990);
+        pretty_assertions::assert_eq!(game.lives, config::DEFAULT_LIVES);
+        game.add_score(10);
+        pretty_assertions::assert_eq!(game.lives, config::DEFAULT_LIVES + 1);
+        assert!(game.extra_life_awarded);
+        // Only one extra life unless they repeat
+        game.add_score(5000);
+        pretty_assertions::assert_eq!(game.lives, config::DEFAULT_LIVES + 1);
+
+        config.extra_life_every = Some(500);
+        let mut game = GameLogic::new(config.clone());
+        game.add_score(2100);
+        pretty_assertions::assert_eq!(game.lives, config::DEFAULT_LIVES + 3);
+
+        // Later mazes of a campaign don't change the interval
+        let mut other = config.clone();
+        other.extra_life_every = None;
+        let mut game = GameLogic::with_campaign(vec![config, other]);
+        game.next_level();
+        game.add_score(2100);
+        pretty_assertions::assert_eq!(game.lives, config::DEFAULT_LIVES + 3);
+    }
+
+    #[test]
+    fn test_frightened_ghosts_flash_before_energised_ends() {
+        let mut game = GameLogic::new(config::Config::empty());
+        assert!(!game.frightened_ghosts_flash());
+        let energised_time = level::level_params(1).energised_time;
+        let flashes = level::level_params(1).energised_flashes;
+        game.energised.update(1, energised_time, 0.0);
+        pretty_assertions::assert_eq!(game.energised_time_left(), energised_time);
+        let mut flash_starts = 0;
+        let mut was_flashing = false;
+        let mut time = 0.0;
+        while game.energised_time_left() > 0.0 {
+            let flashing = game.frightened_ghosts_flash();
+            if flashing && !was_flashing {
+                flash_starts += 1;
+                // Flashing only starts near the end
+                assert!(game.energised_time_left() <= flashes as f32 * ENERGISED_FLASH_PERIOD);
+            }
+            was_flashing = flashing;
+            game.energised.update(0, energised_time, TICK_DELTA);
+            time += TICK_DELTA;
+            assert!(time < energised_time + 1.0);
+        }
+        pretty_assertions::assert_eq!(flash_starts, flashes);
+    }
+
+    #[test]
+    fn test_schedule_alternates_scatter_and_chase() {
+        let mut schedule = Schedule::new(vec![1.0, 2.0]);
+        pretty_assertions::assert_eq!(schedule.mode(), ghost::Mode::Scatter);
+        assert!(!schedule.update(0.5));
+        assert!(schedule.update(0.5));
+        pretty_assertions::assert_eq!(schedule.mode(), ghost::Mode::Chase);
+        assert!(!schedule.update(1.5));
+        assert!(schedule.update(0.5));
+        // Chase forever after the last phase
+        pretty_assertions::assert_eq!(schedule.mode(), ghost::Mode::Chase);
+        assert!(!schedule.update(100.0));
+        pretty_assertions::assert_eq!(schedule.mode(), ghost::Mode::Chase);
+    }
+
+    #[test]
+    fn test_ghosts_reverse_on_phase_switch() {
+        let maze_str = "
+#########
+#M.     #
+#   R   #
+#      B#
+#########
+";
+        let mut config = config::Config::from_string(maze_str).unwrap();
+        config.schedule = Some(vec![1.0, 1.0, 1.0]);
+        let mut game = GameLogic::new(config);
+        game.ghosts[0].mode = ghost::Mode::Scatter;
+        game.ghosts[0].actor.set_pos(5, 3);
+        game.ghosts[0].actor.move_direction = actor::Direction::Left;
+        game.handle_schedule(1.0);
+        pretty_assertions::assert_eq!(game.ghosts[0].mode, ghost::Mode::Chase);
+        pretty_assertions::assert_eq!(game.ghosts[0].actor.move_direction, actor::Direction::Right);
+        // The schedule is paused while Munch is energised
+        game.energised.is_energised = true;
+        game.handle_schedule(1.0);
+        pretty_assertions::assert_eq!(game.schedule.mode(), ghost::Mode::Chase);
+        pretty_assertions::assert_eq!(game.schedule.phase, 1);
+        // A ghost which has just turned out of a corner doesn't reverse into the wall
+        game.energised.is_energised = false;
+        game.ghosts[0].actor.set_pos(7, 3);
+        game.ghosts[0].actor.move_direction = actor::Direction::Left;
+        game.handle_schedule(1.0);
+        pretty_assertions::assert_eq!(game.ghosts[0].mode, ghost::Mode::Scatter);
+        let ghost = &game.ghosts[0].actor;
+        pretty_assertions::assert_eq!(ghost.progress_to_next_square, 0.0);
+        pretty_assertions::assert_eq!(ghost.move_direction, actor::Direction::Up);
+    }
+
+    #[test]
+    fn test_lose_life_respawns_actors() {
+        let maze_str = "
+#######
+#M.. B#
+#  R  #
+#######
+";
+        let mut game = GameLogic::new(config::Config::from_string(maze_str).unwrap());
+        game.munch.set_pos(2, 1);
+        pretty_assertions::assert_eq!(game.maze.eat_dots(&game.munch), 1);
+        game.munch.set_pos(3, 1);
+        game.ghosts[0].actor.set_pos(3, 1);
+        game.score = 20;
+        game.munch_is_dead = true;
+        assert!(game.lose_life());
+        pretty_assertions::assert_eq!(game.lives, config::DEFAULT_LIVES - 1);
+        pretty_assertions::assert_eq!(game.munch.get_pos(), (1, 1));
+        pretty_assertions::assert_eq!(game.ghosts[0].actor.get_pos(), (5, 1));
+        pretty_assertions::assert_eq!(game.score, 20);
+        pretty_assertions::assert_eq!(game.maze.n_dots, 1);
+        assert!(!game.munch_is_dead);
+    }
+
+    #[test]
+    fn test_next_level_reloads_maze() {
+        let maze_str = "
+#######
+#M.. B#
+#  R  #
+#######
+";
+        let mut game = GameLogic::new(config::Config::from_string(maze_str).unwrap());
+        game.munch.set_pos(2, 1);
+        game.maze.eat_dots(&game.munch);
+        game.munch.set_pos(3, 1);
+        game.maze.eat_dots(&game.munch);
+        game.score = 20;
+        assert!(game.is_level_complete());
+        game.next_level();
+        pretty_assertions::assert_eq!(game.level, 2);
+        pretty_assertions::assert_eq!(game.maze.n_dots, 2);
+        pretty_assertions::assert_eq!(game.munch.get_pos(), (1, 1));
+        pretty_assertions::assert_eq!(game.score, 20);
+        pretty_assertions::assert_eq!(game.lives, config::DEFAULT_LIVES);
+        pretty_assertions::assert_eq!(game.ghosts[0].speeds, level::level_params(2).speeds);
+    }
+
+    #[test]
+    fn test_campaign_moves_to_next_maze() {
+        let first = config::Config::from_string("\n#####\n#M.R#\n#####\n").unwrap();
+        let second = config::Config::from_string("\n#####\n#R.M#\n#####\n").unwrap();
+        let mut game = GameLogic::with_campaign(vec![first, second]);
+        pretty_assertions::assert_eq!(game.munch.get_pos(), (1, 1));
+        game.next_level();
+        pretty_assertions::assert_eq!(game.munch.get_pos(), (3, 1));
+        pretty_assertions::assert_eq!(game.maze.respawn_point, (1, 1));
+        game.next_level();
+        pretty_assertions::assert_eq!(game.munch.get_pos(), (1, 1));
+    }
+
+    #[test]
+    fn test_game_over_when_out_of_lives() {
+        let mut game = GameLogic::new(config::Config::empty());
+        for _ in 1..config::DEFAULT_LIVES {
+            assert!(game.lose_life());
+        }
+        assert!(!game.lose_life());
+        assert!(game.is_game_over());
     }
 }
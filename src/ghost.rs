@@ -1,5 +1,8 @@
 use crate::actor;
+use crate::brain;
+use crate::level;
 use crate::maze;
+use crate::rng;
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum Personality {
@@ -12,14 +15,25 @@ pub enum Personality {
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum Mode {
     Chase,
+    /// Head for the ghost's home corner
     Scatter,
+    /// Munch is energised, so the ghost wanders randomly and can be eaten
+    Frightened,
     Eaten,
+    /// Waiting in the ghost house to be released
+    InHouse,
+    /// Heading out through the ghost house door
+    LeavingHouse,
 }
 
 pub struct Ghost {
     pub actor: actor::Actor,
     pub personality: Personality,
+    /// Where the ghost heads while chasing or scattering
+    pub brain: Box<dyn brain::GhostBrain>,
     pub mode: Mode,
+    /// Speeds for the current level
+    pub speeds: level::SpeedTable,
 }
 
 const POSSIBLE_DIRECTIONS: [actor::Direction; 4] = [
@@ -29,47 +43,44 @@ const POSSIBLE_DIRECTIONS: [actor::Direction; 4] = [
     actor::Direction::Down,
 ];
 
-const CLYDE_SCATTER_DIST_SQR: u32 = 8 * 8;
-
 impl Ghost {
     pub fn new(x: i32, y: i32, personality: Personality) -> Ghost {
         Ghost {
             actor: actor::Actor::new(x, y),
             personality,
+            brain: brain::for_personality(personality, 1),
             mode: Mode::Chase,
+            speeds: level::level_params(1).speeds,
         }
     }
 
-    pub fn generate_next_tile(
-        &mut self,
-        maze: &maze::Maze,
-        munch: &actor::Actor,
-        blinky_pos: (i32, i32),
-    ) {
+    pub fn generate_next_tile(&mut self, world: &brain::WorldView, rng: &mut rng::Rng) {
+        let maze = world.maze;
         let target = match self.mode {
-            Mode::Chase => match self.personality {
-                Personality::Blinky => get_blinky_target(munch),
-                Personality::Pinky => get_pinky_target(munch, maze),
-                Personality::Inky => get_inky_target(munch, maze, blinky_pos),
-                Personality::Clyde => self.get_clyde_target(munch, maze),
-            },
-            Mode::Scatter => {
-                // Scatter targets are the corners of the maze
-                match self.personality {
-                    Personality::Blinky => (maze.width - 1, 0),
-                    Personality::Pinky => (0, 0),
-                    Personality::Inky => (maze.width - 1, maze.height - 1),
-                    Personality::Clyde => (0, maze.height - 1),
+            Mode::Frightened => {
+                self.generate_next_tile_random(maze, rng);
+                return;
+            }
+            Mode::Eaten => {
+                if self.generate_next_tile_home(maze) {
+                    return;
                 }
+                // No path home, so head for the respawn point as best we can
+                maze.respawn_point
             }
-            Mode::Eaten => maze.respawn_point,
+            Mode::Chase => self.brain.chase_target(&self.actor, world),
+            Mode::Scatter => self.brain.scatter_target(world),
+            Mode::LeavingHouse => maze.house_exit.unwrap_or(maze.respawn_point),
+            Mode::InHouse => maze.respawn_point,
         };
-        self.generate_next_tile_with_target(maze, &target);
+        self.generate_next_tile_with_target(world, target);
     }
 
-    fn generate_next_tile_with_target(&mut self, maze: &maze::Maze, target: &(i32, i32)) {
+    /// Possible next positions and the directions to reach them, excluding
+    /// turning back the way we came
+    fn next_pos_with_dirs(&self) -> Vec<((i32, i32), actor::Direction)> {
         let ghost_pos = self.actor.get_pos();
-        let next_pos_with_dirs = POSSIBLE_DIRECTIONS
+        POSSIBLE_DIRECTIONS
             .iter()
             .filter_map(|&dir| {
                 if dir == actor::reverse_dir(self.actor.move_direction) {
@@ -78,66 +89,165 @@ impl Ghost {
                     Some((next_pos_from_direction(dir, ghost_pos), dir))
                 }
             })
+            .collect()
+    }
+
+    /// Ghosts only pass through the ghost house door on their way in or out
+    fn can_enter(&self, maze: &maze::Maze, (x, y): (i32, i32)) -> bool {
+        maze.is_ghost_passable(x, y)
+            && (maze.tile(x, y) != maze::Tile::Door
+                || matches!(self.mode, Mode::Eaten | Mode::LeavingHouse))
+    }
+
+    /// Eaten ghosts follow the shortest path back to the respawn point, even if
+    /// that means turning around. Return false if there is no path to follow.
+    fn generate_next_tile_home(&mut self, maze: &maze::Maze) -> bool {
+        let pos = self.actor.get_pos();
+        let Some(next) = maze.next_step_home(pos) else {
+            return false;
+        };
+        // Neighbours are listed in the same order as the directions
+        let Some(i) = maze.neighbours(pos).iter().position(|&n| n == next) else {
+            return false;
+        };
+        self.actor.move_direction = POSSIBLE_DIRECTIONS[i];
+        true
+    }
+
+    /// Frightened ghosts pick a random direction at each intersection
+    fn generate_next_tile_random(&mut self, maze: &maze::Maze, rng: &mut rng::Rng) {
+        let dirs = self
+            .next_pos_with_dirs()
+            .into_iter()
+            .filter(|&(next_pos, _)| self.can_enter(maze, next_pos))
+            .map(|(_, dir)| dir)
             .collect::<Vec<_>>();
-        let mut min_distance_sqr = u32::MAX;
-        for (next_pos, dir) in next_pos_with_dirs {
-            if maze.is_ghost_passable(next_pos.0, next_pos.1) {
-                let d = dist_sqr(&next_pos, target);
-                if d < min_distance_sqr {
-                    min_distance_sqr = d;
-                    self.actor.move_direction = dir;
-                }
-            }
-        }
-        if min_distance_sqr == u32::MAX {
+        self.actor.move_direction = if dirs.is_empty() {
+            actor::reverse_dir(self.actor.move_direction)
+        } else {
+            dirs[rng.below(dirs.len())]
+        };
+    }
+
+    fn generate_next_tile_with_target(&mut self, world: &brain::WorldView, target: (i32, i32)) {
+        let options = self
+            .next_pos_with_dirs()
+            .into_iter()
+            .filter(|&(next_pos, _)| self.can_enter(world.maze, next_pos))
+            .collect::<Vec<_>>();
+        let choice = self
+            .brain
+            .choose_direction(&self.actor, world, &options, target);
+        self.actor.move_direction = match choice {
+            Some(dir) => dir,
             // No valid moves, so reverse direction
-            self.actor.move_direction = actor::reverse_dir(self.actor.move_direction);
-        }
+            None => actor::reverse_dir(self.actor.move_direction),
+        };
     }
 
     pub fn move_along_path(
         &mut self,
-        maze: &maze::Maze,
-        munch: &actor::Actor,
-        blinky_pos: (i32, i32),
+        world: &brain::WorldView,
+        rng: &mut rng::Rng,
         time_delta: f32,
     ) {
+        let maze = world.maze;
+        if self.mode == Mode::InHouse {
+            return;
+        }
+        self.actor.speed = self.current_speed(maze);
         let changed_discrete_position =
             self.actor
                 .walk_no_collisions(self.actor.move_direction, maze, time_delta);
         if changed_discrete_position {
-            self.generate_next_tile(maze, munch, blinky_pos);
+            self.generate_next_tile(world, rng);
+        }
+    }
+
+    /// Eyes rush home, and otherwise ghosts slow down in tunnels and while
+    /// frightened. Blinky speeds up when few dots remain.
+    fn current_speed(&self, maze: &maze::Maze) -> f32 {
+        let (x, y) = self.actor.get_pos();
+        match self.mode {
+            Mode::Eaten => self.speeds.ghost_eaten,
+            _ if maze.is_tunnel(x, y) => self.speeds.ghost_tunnel,
+            Mode::Frightened => self.speeds.ghost_frightened,
+            Mode::Chase | Mode::Scatter
+                if self.personality == Personality::Blinky
+                    && maze.n_dots <= self.speeds.elroy_dots =>
+            {
+                self.speeds.elroy
+            }
+            _ => self.speeds.ghost,
+        }
+    }
+
+    /// Switch between scatter and chase, turning around to signal the change.
+    /// Frightened and eaten ghosts carry on, and pick up the new mode later.
+    pub fn set_scheduled_mode(&mut self, mode: Mode, world: &brain::WorldView, rng: &mut rng::Rng) {
+        if matches!(self.mode, Mode::Chase | Mode::Scatter) && self.mode != mode {
+            self.mode = mode;
+            self.turn_around(world, rng);
+        }
+    }
+
+    /// Turn back the way the ghost is going. A ghost which has only just left
+    /// a corner may have a wall behind it, in which case it picks a new way
+    /// out of its tile instead, other than straight on.
+    fn turn_around(&mut self, world: &brain::WorldView, rng: &mut rng::Rng) {
+        let behind = next_pos_from_direction(
+            actor::reverse_dir(self.actor.move_direction),
+            self.actor.get_pos(),
+        );
+        if self.actor.progress_to_next_square > 0.0 || self.can_enter(world.maze, behind) {
+            self.actor.reverse(world.maze);
+        } else {
+            self.actor.move_direction = actor::reverse_dir(self.actor.move_direction);
+            self.generate_next_tile(world, rng);
+        }
+    }
+
+    /// Send the ghost out of the ghost house, or straight into `mode` if the
+    /// maze has no door
+    pub fn leave_house(&mut self, maze: &maze::Maze, mode: Mode) {
+        self.mode = match maze.house_exit {
+            Some(_) => Mode::LeavingHouse,
+            None => mode,
+        };
+    }
+
+    /// Take up `mode` if the ghost has made it out of the ghost house
+    pub fn update_leaving_house(&mut self, maze: &maze::Maze, mode: Mode) {
+        if self.mode == Mode::LeavingHouse && maze.house_exit == Some(self.actor.get_pos()) {
+            log::info!("{:?} has left the ghost house", self.personality);
+            self.mode = mode;
         }
     }
 
-    pub fn set_mode_scatter(&mut self) {
-        if self.mode != Mode::Eaten {
-            self.mode = Mode::Scatter;
+    /// Frighten the ghost, turning it around as it starts to flee.
+    /// Ghosts in or leaving the ghost house are unaffected.
+    pub fn set_mode_frightened(&mut self, world: &brain::WorldView, rng: &mut rng::Rng) {
+        if matches!(self.mode, Mode::Chase | Mode::Scatter) {
+            self.mode = Mode::Frightened;
+            self.turn_around(world, rng);
         }
     }
 
-    pub fn set_mode_chase(&mut self) {
-        if self.mode != Mode::Eaten {
-            self.mode = Mode::Chase;
+    /// Return to scatter or chase once Munch is no longer energised
+    pub fn end_frightened(&mut self, mode: Mode) {
+        if self.mode == Mode::Frightened {
+            self.mode = mode;
         }
     }
 
-    /// Returns false if the ghost was already eaten, and true otherwise
+    /// Returns true if the ghost was frightened and so could be eaten, and false otherwise
     pub fn eat_ghost(&mut self) -> bool {
-        if self.mode == Mode::Eaten {
+        if self.mode != Mode::Frightened {
             return false;
         }
         self.mode = Mode::Eaten;
         true
     }
-
-    fn get_clyde_target(&self, munch: &actor::Actor, maze: &maze::Maze) -> (i32, i32) {
-        if dist_sqr(&munch.get_pos(), &self.actor.get_pos()) <= CLYDE_SCATTER_DIST_SQR {
-            (0, maze.height - 1)
-        } else {
-            munch.get_pos()
-        }
-    }
 }
 
 fn next_pos_from_direction(dir: actor::Direction, ghost_pos: (i32, i32)) -> (i32, i32) {
@@ -150,79 +260,6 @@ fn next_pos_from_direction(dir: actor::Direction, ghost_pos: (i32, i32)) -> (i32
     }
 }
 
-/// Blinky directly targets the player's current position.
-fn get_blinky_target(munch: &actor::Actor) -> (i32, i32) {
-    munch.get_pos()
-}
-
-/// Pinky tries to move towards the tile four spaces ahead of the player.
-fn get_lookahead_target(munch: &actor::Actor, maze: &maze::Maze, lookahead: i32) -> (i32, i32) {
-    let (mut x, mut y) = munch.get_pos();
-    for i in (1..lookahead + 1).rev() {
-        match munch.move_direction {
-            actor::Direction::Up => {
-                if y < i {
-                    y += maze.height;
-                }
-                if maze.is_ghost_passable(x, y - i) {
-                    return (x, y - i);
-                }
-            }
-            actor::Direction::Down => {
-                if maze.is_ghost_passable(x, y + i) {
-                    return (x, y + i);
-                }
-            }
-            actor::Direction::Left => {
-                if x < i {
-                    x += maze.width;
-                }
-                if maze.is_ghost_passable(x - i, y) {
-                    return (x - i, y);
-                }
-            }
-            actor::Direction::Right => {
-                if maze.is_ghost_passable(x + i, y) {
-                    return (x + i, y);
-                }
-            }
-            _ => {}
-        }
-    }
-    (x % maze.width, y % maze.height)
-}
-
-/// Squared distance between two points
-fn dist_sqr(a: &(i32, i32), b: &(i32, i32)) -> u32 {
-    let dx = a.0 as isize - b.0 as isize;
-    let dy = a.1 as isize - b.1 as isize;
-    (dx * dx + dy * dy) as u32
-}
-
-const PINKY_LOOKAHEAD: i32 = 4;
-
-fn get_pinky_target(munch: &actor::Actor, maze: &maze::Maze) -> (i32, i32) {
-    get_lookahead_target(munch, maze, PINKY_LOOKAHEAD)
-}
-
-const INKY_LOOKAHEAD: i32 = 2;
-
-/// Inky targets a position based on the player's position and Blinky's position.
-fn get_inky_target(munch: &actor::Actor, maze: &maze::Maze, blinky_pos: (i32, i32)) -> (i32, i32) {
-    let (mut x, mut y) = get_lookahead_target(munch, maze, INKY_LOOKAHEAD);
-    if x < blinky_pos.0 {
-        x += maze.width + x - blinky_pos.0;
-    } else {
-        x += x - blinky_pos.0;
-    }
-    if y < blinky_pos.1 {
-        y += maze.height + y - blinky_pos.1;
-    } else {
-        y += y - blinky_pos.1;
-    }
-    (x % maze.width, y % maze.height)
-}
-
 #[cfg(test)]
 use crate::config;
 
@@ -231,45 +268,142 @@ mod tests {
     use super::*;
 
     #[test]
-    fn test_pinky_target() {
+    fn test_eaten_ghost_finds_way_home() {
+        // Steering greedily towards the respawn point keeps the ghost going
+        // round the loop, as the way out always looks further away
         let maze_str = "
 ###########
-#         #
-#    R    #
-#         #
+#R#########
+#.#.......#
+#.#.#####.#
+#.#.#####.#
+#.#.......#
+#.#######.#
+#...M.....#
 ###########
 ";
         let maze = config::Config::from_string(maze_str).unwrap().maze;
-        let mut munch = actor::Actor::new(4, 1);
-        munch.move_direction = actor::Direction::Right;
-        let target = get_pinky_target(&munch, &maze);
-        pretty_assertions::assert_eq!(target, (4 + PINKY_LOOKAHEAD, 1));
-        munch.move_direction = actor::Direction::Down;
-        let target = get_pinky_target(&munch, &maze);
-        pretty_assertions::assert_eq!(target, (4, 3));
+        let munch = actor::Actor::new(4, 7);
+        let mut ghost = Ghost::new(3, 2, Personality::Blinky);
+        let mut rng = rng::Rng::new(0);
+        ghost.mode = Mode::Eaten;
+        ghost.actor.move_direction = actor::Direction::Right;
+        for _ in 0..50 {
+            ghost.generate_next_tile(
+                &brain::WorldView {
+                    maze: &maze,
+                    munch: &munch,
+                    ghosts: &[],
+                },
+                &mut rng,
+            );
+            let (x, y) = next_pos_from_direction(ghost.actor.move_direction, ghost.actor.get_pos());
+            ghost.actor.set_pos(x, y);
+            if ghost.actor.get_pos() == maze.respawn_point {
+                break;
+            }
+        }
+        pretty_assertions::assert_eq!(ghost.actor.get_pos(), maze.respawn_point);
     }
 
     #[test]
-    fn test_inky_target() {
+    fn test_ghost_speeds() {
         let maze_str = "
-###########
-#         #
-#         #
-#    R    #
-#         #
-#         #
-###########
+#######
+   .   
+###R###
+#M    #
+#######
+";
+        let mut maze = config::Config::from_string(maze_str).unwrap().maze;
+        let mut ghost = Ghost::new(3, 1, Personality::Blinky);
+        let speeds = ghost.speeds;
+        maze.n_dots = speeds.elroy_dots + 1;
+        ghost.mode = Mode::Chase;
+        pretty_assertions::assert_eq!(ghost.current_speed(&maze), speeds.ghost);
+        ghost.mode = Mode::Frightened;
+        ghost.actor.set_pos(0, 1);
+        pretty_assertions::assert_eq!(ghost.current_speed(&maze), speeds.ghost_tunnel);
+        ghost.actor.set_pos(3, 3);
+        pretty_assertions::assert_eq!(ghost.current_speed(&maze), speeds.ghost_frightened);
+        ghost.mode = Mode::Eaten;
+        pretty_assertions::assert_eq!(ghost.current_speed(&maze), speeds.ghost_eaten);
+        // Cruise Elroy only applies to Blinky
+        ghost.mode = Mode::Chase;
+        maze.n_dots = speeds.elroy_dots;
+        pretty_assertions::assert_eq!(ghost.current_speed(&maze), speeds.elroy);
+        ghost.personality = Personality::Pinky;
+        pretty_assertions::assert_eq!(ghost.current_speed(&maze), speeds.ghost);
+    }
+
+    #[test]
+    fn test_ghost_reversing_at_corner_avoids_wall() {
+        let maze_str = "
+#######
+#     #
+# ### #
+#M   R#
+#.#####
 ";
         let maze = config::Config::from_string(maze_str).unwrap().maze;
-        let mut munch = actor::Actor::new(4, 3);
-        munch.move_direction = actor::Direction::Right;
-        let blinky_pos = (2, 2);
-        let target = get_inky_target(&munch, &maze, blinky_pos);
-        pretty_assertions::assert_eq!(target, (10, 4));
-        let mut munch = actor::Actor::new(4, 1);
-        munch.move_direction = actor::Direction::Up;
-        let blinky_pos = (1, 4);
-        let target = get_inky_target(&munch, &maze, blinky_pos);
-        pretty_assertions::assert_eq!(target, (7, 5));
+        let munch = actor::Actor::new(1, 3);
+        let world = brain::WorldView {
+            maze: &maze,
+            munch: &munch,
+            ghosts: &[],
+        };
+        // Just turned right out of the top left corner, with a wall behind
+        let mut ghost = Ghost::new(1, 1, Personality::Blinky);
+        ghost.actor.move_direction = actor::Direction::Right;
+        ghost.set_mode_frightened(&world, &mut rng::Rng::new(0));
+        let (x, y) = next_pos_from_direction(ghost.actor.move_direction, ghost.actor.get_pos());
+        assert!(maze.is_ghost_passable(x, y));
+        pretty_assertions::assert_eq!(ghost.actor.move_direction, actor::Direction::Down);
+    }
+
+    #[test]
+    fn test_frightened_ghost_reverses_then_turns_randomly() {
+        let maze_str = "
+#######
+#     #
+# # # #
+#M   R#
+#.#####
+";
+        let maze = config::Config::from_string(maze_str).unwrap().maze;
+        let munch = actor::Actor::new(1, 3);
+        let mut ghost = Ghost::new(3, 1, Personality::Blinky);
+        let mut rng = rng::Rng::new(0);
+        ghost.actor.move_direction = actor::Direction::Right;
+        ghost.set_mode_frightened(
+            &brain::WorldView {
+                maze: &maze,
+                munch: &munch,
+                ghosts: &[],
+            },
+            &mut rng,
+        );
+        pretty_assertions::assert_eq!(ghost.actor.move_direction, actor::Direction::Left);
+        let mut turns = std::collections::HashSet::new();
+        for _ in 0..200 {
+            let previous = ghost.actor.move_direction;
+            let (x, y) = next_pos_from_direction(previous, ghost.actor.get_pos());
+            ghost.actor.set_pos(x, y);
+            ghost.generate_next_tile(
+                &brain::WorldView {
+                    maze: &maze,
+                    munch: &munch,
+                    ghosts: &[],
+                },
+                &mut rng,
+            );
+            let (x, y) = next_pos_from_direction(ghost.actor.move_direction, ghost.actor.get_pos());
+            assert!(maze.is_ghost_passable(x, y));
+            if ghost.actor.get_pos() == (3, 1) {
+                turns.insert(ghost.actor.move_direction);
+            }
+        }
+        // Over enough visits to the intersection, every way out is taken
+        pretty_assertions::assert_eq!(turns.len(), 3);
     }
 }
@@ -0,0 +1,122 @@
+use crate::{ghost, level};
+
+/// Order the ghosts are let out of the ghost house in. Blinky normally starts
+/// outside the house, and if a maze puts him inside he is let out as soon as
+/// the door is clear, without holding up the others.
+pub const RELEASE_ORDER: [ghost::Personality; 3] = [
+    ghost::Personality::Pinky,
+    ghost::Personality::Inky,
+    ghost::Personality::Clyde,
+];
+
+/// Decides when the ghosts waiting in the ghost house are let out.
+/// Each ghost leaves once Munch has eaten enough dots since the previous
+/// ghost left, or once Munch has gone too long without eating a dot.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct GhostHouse {
+    /// Dots eaten since the last ghost was let out
+    pub dot_counter: u32,
+    /// Seconds since Munch last ate a dot
+    pub idle_timer: f32,
+}
+
+impl GhostHouse {
+    pub fn new() -> Self {
+        GhostHouse::default()
+    }
+
+    /// Return the index of the ghost to release this update, if any.
+    /// Only one ghost leaves at a time.
+    pub fn update(
+        &mut self,
+        ghosts: &[ghost::Ghost],
+        dots_eaten: i32,
+        params: &level::LevelParams,
+        time_delta: f32,
+    ) -> Option<usize> {
+        if dots_eaten > 0 {
+            self.dot_counter += dots_eaten as u32;
+            self.idle_timer = 0.0;
+        } else {
+            self.idle_timer += time_delta;
+        }
+        if ghosts.iter().any(|g| g.mode == ghost::Mode::LeavingHouse) {
+            return None;
+        }
+        let unordered = ghosts.iter().position(|g| {
+            g.mode == ghost::Mode::InHouse && !RELEASE_ORDER.contains(&g.personality)
+        });
+        if unordered.is_some() {
+            return unordered;
+        }
+        let (order, index) = RELEASE_ORDER.iter().enumerate().find_map(|(order, &p)| {
+            ghosts
+                .iter()
+                .position(|g| g.personality == p && g.mode == ghost::Mode::InHouse)
+                .map(|index| (order, index))
+        })?;
+        if self.dot_counter < params.release_dots[order] && self.idle_timer < params.release_timeout
+        {
+            return None;
+        }
+        self.dot_counter = 0;
+        self.idle_timer = 0.0;
+        Some(index)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{config, game_logic};
+
+    const MAZE: &str = "
+###########
+#M........#
+#.###=###.#
+#.#PBRIC#.#
+#.#######.#
+#.........#
+###########
+";
+
+    #[test]
+    fn test_ghosts_released_one_at_a_time() {
+        let mut game = game_logic::GameLogic::new(config::Config::from_string(MAZE).unwrap());
+        pretty_assertions::assert_eq!(game.maze.house_exit, Some((5, 1)));
+        assert!(game.ghosts.iter().all(|g| g.mode == ghost::Mode::InHouse));
+        game.tick();
+        // Blinky is let out straight away, and nobody else follows until he is clear
+        pretty_assertions::assert_eq!(game.ghosts[1].mode, ghost::Mode::LeavingHouse);
+        for _ in 0..2 * game_logic::TICK_RATE {
+            game.tick();
+            if game.ghosts[1].mode != ghost::Mode::LeavingHouse {
+                break;
+            }
+            pretty_assertions::assert_eq!(game.ghosts[0].mode, ghost::Mode::InHouse);
+        }
+        pretty_assertions::assert_eq!(game.ghosts[1].actor.get_pos(), (5, 1));
+        game.tick();
+        pretty_assertions::assert_eq!(game.ghosts[0].mode, ghost::Mode::LeavingHouse);
+    }
+
+    #[test]
+    fn test_release_waits_for_dots_or_timeout() {
+        let game = game_logic::GameLogic::new(config::Config::from_string(MAZE).unwrap());
+        let mut ghosts = game.ghosts;
+        let params = level::level_params(1);
+        let mut house = GhostHouse::new();
+        // Blinky starting in the house doesn't count towards the release order
+        pretty_assertions::assert_eq!(house.update(&ghosts, 0, &params, 0.1), Some(1));
+        ghosts[1].mode = ghost::Mode::Chase;
+        pretty_assertions::assert_eq!(house.update(&ghosts, 0, &params, 0.1), Some(0));
+        ghosts[0].mode = ghost::Mode::Chase;
+        // Inky needs 30 dots on level 1
+        pretty_assertions::assert_eq!(house.update(&ghosts, 29, &params, 0.1), None);
+        pretty_assertions::assert_eq!(house.update(&ghosts, 1, &params, 0.1), Some(2));
+        ghosts[2].mode = ghost::Mode::Chase;
+        // Clyde is released if Munch stops eating dots for long enough
+        pretty_assertions::assert_eq!(house.update(&ghosts, 0, &params, 2.0), None);
+        pretty_assertions::assert_eq!(house.update(&ghosts, 0, &params, 2.0), Some(3));
+    }
+}
@@ -0,0 +1,184 @@
+use crate::fruit;
+
+/// Speeds of Munch and the ghosts in each situation, as multipliers of the
+/// base movement speed
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct SpeedTable {
+    pub munch: f32,
+    /// Munch while moving onto a dot or power pellet
+    pub munch_eating: f32,
+    pub ghost: f32,
+    /// Ghosts in a tunnel leading off the edge of the maze
+    pub ghost_tunnel: f32,
+    pub ghost_frightened: f32,
+    /// Eyes returning to the ghost house
+    pub ghost_eaten: f32,
+    /// Blinky speeds up to `elroy` once this many dots or fewer remain
+    pub elroy_dots: i32,
+    pub elroy: f32,
+}
+
+/// Parameters which make the game harder as Munch clears more mazes
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct LevelParams {
+    pub speeds: SpeedTable,
+    /// How long the ghosts can be eaten for after a power pellet, in seconds
+    pub energised_time: f32,
+    /// Number of times frightened ghosts flash white before Munch stops being energised
+    pub energised_flashes: u32,
+    /// Durations of the alternating scatter and chase phases, starting with
+    /// scatter, in seconds. The ghosts chase forever after the last phase.
+    pub schedule: &'static [f32],
+    /// Dots Munch must eat before each ghost leaves the ghost house, counted
+    /// from when the previous ghost left, in the order of `ghost_house::RELEASE_ORDER`
+    pub release_dots: [u32; 3],
+    /// Seconds without Munch eating a dot before the next ghost is released anyway
+    pub release_timeout: f32,
+    /// Clyde gives up chasing for his corner once this close to Munch, in tiles
+    pub clyde_shy_distance: i32,
+    /// Bonus fruit which appears in this level
+    pub fruit: fruit::Kind,
+}
+
+const EARLY_SCHEDULE: &[f32] = &[7.0, 20.0, 7.0, 20.0, 5.0, 20.0, 5.0];
+const LATE_SCHEDULE: &[f32] = &[5.0, 20.0, 5.0, 20.0, 5.0, 60.0, 1.0];
+
+const LEVELS: [LevelParams; 5] = [
+    LevelParams {
+        speeds: SpeedTable {
+            munch: 1.0,
+            munch_eating: 0.9,
+            ghost: 0.95,
+            ghost_tunnel: 0.5,
+            ghost_frightened: 0.6,
+            ghost_eaten: 2.0,
+            elroy_dots: 20,
+            elroy: 1.0,
+        },
+        energised_time: 10.0,
+        energised_flashes: 5,
+        release_dots: [0, 30, 60],
+        release_timeout: 4.0,
+        clyde_shy_distance: 8,
+        fruit: fruit::Kind::Cherry,
+        schedule: EARLY_SCHEDULE,
+    },
+    LevelParams {
+        speeds: SpeedTable {
+            munch: 1.1,
+            munch_eating: 1.0,
+            ghost: 1.05,
+            ghost_tunnel: 0.55,
+            ghost_frightened: 0.65,
+            ghost_eaten: 2.0,
+            elroy_dots: 30,
+            elroy: 1.1,
+        },
+        energised_time: 8.0,
+        energised_flashes: 5,
+        release_dots: [0, 0, 50],
+        release_timeout: 4.0,
+        clyde_shy_distance: 8,
+        fruit: fruit::Kind::Strawberry,
+        schedule: EARLY_SCHEDULE,
+    },
+    LevelParams {
+        speeds: SpeedTable {
+            munch: 1.1,
+            munch_eating: 1.0,
+            ghost: 1.1,
+            ghost_tunnel: 0.55,
+            ghost_frightened: 0.65,
+            ghost_eaten: 2.0,
+            elroy_dots: 40,
+            elroy: 1.15,
+        },
+        energised_time: 6.0,
+        energised_flashes: 5,
+        release_dots: [0, 0, 0],
+        release_timeout: 4.0,
+        clyde_shy_distance: 7,
+        fruit: fruit::Kind::Orange,
+        schedule: LATE_SCHEDULE,
+    },
+    LevelParams {
+        speeds: SpeedTable {
+            munch: 1.15,
+            munch_eating: 1.05,
+            ghost: 1.15,
+            ghost_tunnel: 0.6,
+            ghost_frightened: 0.7,
+            ghost_eaten: 2.0,
+            elroy_dots: 40,
+            elroy: 1.2,
+        },
+        energised_time: 4.0,
+        energised_flashes: 5,
+        release_dots: [0, 0, 0],
+        release_timeout: 4.0,
+        clyde_shy_distance: 6,
+        fruit: fruit::Kind::Apple,
+        schedule: LATE_SCHEDULE,
+    },
+    LevelParams {
+        speeds: SpeedTable {
+            munch: 1.25,
+            munch_eating: 1.1,
+            ghost: 1.2,
+            ghost_tunnel: 0.6,
+            ghost_frightened: 0.75,
+            ghost_eaten: 2.0,
+            elroy_dots: 50,
+            elroy: 1.25,
+        },
+        energised_time: 2.0,
+        energised_flashes: 3,
+        release_dots: [0, 0, 0],
+        release_timeout: 3.0,
+        clyde_shy_distance: 5,
+        fruit: fruit::Kind::Melon,
+        schedule: LATE_SCHEDULE,
+    },
+];
+
+/// Get the parameters for a level, starting from level 1.
+/// Levels past the end of the table use the final entry.
+pub fn level_params(level: u32) -> LevelParams {
+    let index = (level.max(1) as usize - 1).min(LEVELS.len() - 1);
+    LEVELS[index]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_levels_get_harder() {
+        for level in 1..LEVELS.len() as u32 {
+            let current = level_params(level);
+            let next = level_params(level + 1);
+            assert!(next.speeds.ghost > current.speeds.ghost);
+            assert!(next.speeds.elroy_dots >= current.speeds.elroy_dots);
+            assert!(next.energised_time < current.energised_time);
+            assert!(next.clyde_shy_distance <= current.clyde_shy_distance);
+        }
+    }
+
+    #[test]
+    fn test_speed_tables_are_consistent() {
+        for params in LEVELS {
+            let speeds = params.speeds;
+            assert!(speeds.munch_eating < speeds.munch);
+            assert!(speeds.ghost_tunnel < speeds.ghost);
+            assert!(speeds.ghost_frightened < speeds.ghost);
+            assert!(speeds.ghost_eaten > speeds.munch);
+            assert!(speeds.elroy >= speeds.ghost);
+        }
+    }
+
+    #[test]
+    fn test_level_params_clamped() {
+        pretty_assertions::assert_eq!(level_params(0), LEVELS[0]);
+        pretty_assertions::assert_eq!(level_params(100), LEVELS[LEVELS.len() - 1]);
+    }
+}
@@ -0,0 +1,22 @@
+//! Headless simulation of Munch, with no dependency on ggez.
+//!
+//! A game is driven by creating a [`game_logic::GameLogic`] from a
+//! [`config::Config`], steering Munch with
+//! [`game_logic::GameLogic::set_direction`] and advancing time with
+//! [`game_logic::GameLogic::update`].
+
+pub mod actor;
+pub mod bot;
+pub mod brain;
+pub mod config;
+pub mod env;
+pub mod fruit;
+pub mod game_logic;
+pub mod ghost;
+pub mod ghost_house;
+pub mod level;
+pub mod maze;
+pub mod replay;
+pub mod rng;
+pub mod snapshot;
+pub mod validate;
@@ -1,18 +1,17 @@
-use clap::Parser;
+use clap::{Parser, Subcommand};
 use ggez::conf;
 use ggez::event::{self, EventLoop};
 use ggez::{Context, ContextBuilder};
 
-mod actor;
+use munch::{config, replay, snapshot, validate};
+
 mod audio;
-mod config;
 mod game;
-mod game_logic;
-mod ghost;
-mod maze;
 mod spritesheet;
 mod window;
 
+const DEFAULT_MAZE: &str = include_str!("../resources/maze.txt");
+
 fn init_logger(log_level: log::LevelFilter) {
     let mut builder = colog::basic_builder();
     builder.filter_level(log_level);
@@ -23,8 +22,80 @@ fn init_logger(log_level: log::LevelFilter) {
 #[derive(Parser, Debug)]
 #[command(version, about, long_about = None)] // Reads version from Cargo.toml, uses doc comment for about
 struct Cli {
+    #[command(subcommand)]
+    command: Option<Command>,
     #[arg(short, long, default_value_t = log::LevelFilter::Warn)]
     log_level: log::LevelFilter,
+    /// Maze file to play, or a directory of maze files to play in order as a campaign
+    #[arg(short, long, value_name = "PATH")]
+    maze: Option<std::path::PathBuf>,
+    /// Number of lives Munch starts with
+    #[arg(long, default_value_t = config::DEFAULT_LIVES)]
+    lives: u32,
+    /// Score at which Munch earns an extra life, or 0 for none
+    #[arg(long, value_name = "SCORE", default_value_t = config::DEFAULT_EXTRA_LIFE)]
+    extra_life: u32,
+    /// Award a further extra life every this many points after the first
+    #[arg(long, value_name = "POINTS")]
+    extra_life_every: Option<u32>,
+    /// How close Munch and a ghost must come to collide, in tiles, overriding
+    /// every maze's setting
+    #[arg(long, value_name = "TILES")]
+    collision_radius: Option<f32>,
+    /// Record the inputs of each game to a replay file
+    #[arg(long, value_name = "FILE")]
+    record: Option<std::path::PathBuf>,
+    /// Play back a replay file instead of taking input from the keyboard
+    #[arg(long, value_name = "FILE")]
+    replay: Option<std::path::PathBuf>,
+    /// Resume a game saved with F5. F5 and F9 then save to and load from this file.
+    #[arg(long, value_name = "FILE")]
+    load_state: Option<std::path::PathBuf>,
+    /// Let the computer play Munch, e.g. to soak test a maze or as a demo
+    #[arg(long)]
+    autoplay: bool,
+    /// Seed for the game's random choices, picked from the clock if not given
+    #[arg(long)]
+    seed: Option<u64>,
+    /// Speed up the simulation by this factor, e.g. to skip through replays
+    #[arg(long, default_value_t = 1)]
+    fast_forward: u32,
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Check that a maze, or every maze in a directory, can be completed
+    Validate { maze: std::path::PathBuf },
+}
+
+/// Validate every maze at `path`, printing any problems.
+/// Return whether all the mazes are valid.
+fn run_validate(path: &std::path::Path) -> bool {
+    let paths = match config::campaign_paths(path) {
+        Ok(paths) => paths,
+        Err(e) => {
+            eprintln!("{}", e);
+            return false;
+        }
+    };
+    let mut all_valid = true;
+    for path in paths {
+        let result = config::Config::from_file(&path).map(|config| validate::validate(&config));
+        match result {
+            Ok(Ok(())) => println!("{}: OK", path.display()),
+            Ok(Err(errors)) => {
+                all_valid = false;
+                for e in errors {
+                    println!("{}: {}", path.display(), e);
+                }
+            }
+            Err(e) => {
+                all_valid = false;
+                println!("{}", e);
+            }
+        }
+    }
+    all_valid
 }
 
 fn init_context() -> (Context, EventLoop<()>) {
@@ -39,22 +110,88 @@ fn init_context() -> (Context, EventLoop<()>) {
         .expect("Could not create ggez context")
 }
 
-fn init_config() -> config::Config {
-    // match config::Config::from_file("resources/maze.txt") {
-    match config::Config::from_string(include_str!("../resources/maze.txt")) {
-        Ok(config) => config,
+fn init_campaign(maze: Option<&std::path::Path>) -> Vec<config::Config> {
+    let campaign = match maze {
+        Some(path) => config::load_campaign(path),
+        None => config::Config::from_string(DEFAULT_MAZE)
+            .map(|c| vec![c])
+            .map_err(|e| e.render(DEFAULT_MAZE)),
+    };
+    let campaign = match campaign {
+        Ok(campaign) => campaign,
         Err(e) => {
             log::error!("Error loading config: {}", e);
             std::process::exit(1);
         }
+    };
+    let mut valid = true;
+    for (i, config) in campaign.iter().enumerate() {
+        if let Err(errors) = validate::validate(config) {
+            valid = false;
+            for e in errors {
+                log::error!("Maze {} is unwinnable: {}", i + 1, e);
+            }
+        }
+    }
+    if !valid {
+        std::process::exit(1);
+    }
+    campaign
+}
+
+fn init_replay(path: &std::path::Path) -> replay::Replay {
+    match replay::Replay::from_file(path) {
+        Ok(replay) => replay,
+        Err(e) => {
+            log::error!("Error loading replay: {}", e);
+            std::process::exit(1);
+        }
+    }
+}
+
+fn init_snapshot(path: &std::path::Path) -> snapshot::Snapshot {
+    match snapshot::Snapshot::from_file(path) {
+        Ok(snapshot) => snapshot,
+        Err(e) => {
+            log::error!("Error loading snapshot: {}", e);
+            std::process::exit(1);
+        }
     }
 }
 
 fn main() {
     let cli = Cli::parse();
     init_logger(cli.log_level);
+    if let Some(Command::Validate { maze }) = &cli.command {
+        std::process::exit(if run_validate(maze) { 0 } else { 1 });
+    }
+    let mut campaign = init_campaign(cli.maze.as_deref());
+    for config in &mut campaign {
+        config.starting_lives = cli.lives;
+        config.extra_life = Some(cli.extra_life).filter(|&score| score > 0);
+        config.extra_life_every = cli.extra_life_every;
+        if let Some(radius) = cli.collision_radius {
+            config.collision_radius = radius;
+        }
+    }
+    let seed = cli.seed.unwrap_or_else(|| {
+        std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map_or(0, |d| d.as_nanos() as u64)
+    });
+    log::info!("Using seed {}", seed);
+    let options = game::Options {
+        seed,
+        record: cli.record,
+        replay: cli.replay.as_deref().map(init_replay),
+        snapshot: cli.load_state.as_deref().map(init_snapshot),
+        snapshot_path: cli
+            .load_state
+            .unwrap_or_else(|| game::DEFAULT_SNAPSHOT_PATH.into()),
+        autoplay: cli.autoplay,
+        fast_forward: cli.fast_forward,
+    };
     let (mut ctx, event_loop) = init_context();
-    let config = init_config();
-    let game = game::Game::new(&mut ctx, config);
+    let game = game::Game::new(&mut ctx, campaign, options);
     event::run(ctx, event_loop, game);
 }
@@ -1,10 +1,20 @@
+use std::collections::HashMap;
+
+use pathfinding::directed::dijkstra::dijkstra_all;
+
 use crate::actor;
 
+/// How far Munch must be into a tile to eat what is on it
+pub const EATING_THRESHOLD: f32 = 0.45;
+
 #[derive(Clone, Copy, PartialEq, Eq, Debug)]
 pub enum Tile {
     Wall,
     Path,
     PlayerImpassable,
+    /// Entrance to the ghost house, which ghosts only pass through when
+    /// leaving the house or returning to it after being eaten
+    Door,
     Respawn,
     Dot,
     PowerPellet,
@@ -17,29 +27,97 @@ pub fn player_passable(tile: &Tile) -> bool {
 pub fn ghost_passable(tile: &Tile) -> bool {
     matches!(
         tile,
-        Tile::Path | Tile::PlayerImpassable | Tile::Dot | Tile::PowerPellet | Tile::Respawn
+        Tile::Path
+            | Tile::PlayerImpassable
+            | Tile::Door
+            | Tile::Dot
+            | Tile::PowerPellet
+            | Tile::Respawn
     )
 }
 
-#[derive(Debug)]
+#[derive(Clone, Debug)]
 pub struct Maze {
     pub width: i32,
     pub height: i32,
     maze: Vec<Tile>,
     pub respawn_point: (i32, i32),
+    /// The tile just outside the ghost house door, if there is a door
+    pub house_exit: Option<(i32, i32)>,
+    /// Whether each tile is part of a tunnel leading off the edge of the maze
+    tunnels: Vec<bool>,
+    /// The next tile on the shortest ghost path from each tile to the respawn point
+    paths_home: HashMap<(i32, i32), (i32, i32)>,
     pub n_dots: i32,
 }
 
 impl Maze {
     pub fn new(width: i32, height: i32, maze: Vec<Tile>, respawn_point: (i32, i32)) -> Self {
         let n_dots = maze.iter().filter(|&&t| t == Tile::Dot).count() as i32;
-        Maze {
+        let mut maze = Maze {
             width,
             height,
             maze,
             respawn_point,
+            house_exit: None,
+            tunnels: Vec::new(),
+            paths_home: HashMap::new(),
             n_dots,
+        };
+        maze.house_exit = maze.find_house_exit();
+        maze.tunnels = maze.find_tunnels();
+        maze.paths_home = maze.find_paths_home();
+        maze
+    }
+
+    /// Search outwards from the respawn point once, so that every tile's
+    /// parent in the search is its next step along a shortest path home.
+    /// Eating dots doesn't change where ghosts can go, so this never goes stale.
+    fn find_paths_home(&self) -> HashMap<(i32, i32), (i32, i32)> {
+        dijkstra_all(&self.respawn_point, |&pos| {
+            self.neighbours(pos)
+                .into_iter()
+                .filter(|&(x, y)| self.is_ghost_passable(x, y))
+                .map(|next| (next, 1))
+        })
+        .into_iter()
+        .map(|(pos, (next, _))| (pos, next))
+        .collect()
+    }
+
+    /// A tunnel is a corridor one tile wide running straight in from a gap in
+    /// the edge of the maze
+    fn find_tunnels(&self) -> Vec<bool> {
+        let mut tunnels = vec![false; self.maze.len()];
+        let mut mark = |start: (i32, i32), step: (i32, i32), side: (i32, i32)| {
+            let (mut x, mut y) = start;
+            while (0..self.width).contains(&x)
+                && (0..self.height).contains(&y)
+                && self.is_ghost_passable(x, y)
+                && !self.is_ghost_passable(x + side.0, y + side.1)
+                && !self.is_ghost_passable(x - side.0, y - side.1)
+            {
+                tunnels[self.index(x, y)] = true;
+                x += step.0;
+                y += step.1;
+            }
+        };
+        for y in 0..self.height {
+            mark((0, y), (1, 0), (0, 1));
+            mark((self.width - 1, y), (-1, 0), (0, 1));
+        }
+        for x in 0..self.width {
+            mark((x, 0), (0, 1), (1, 0));
+            mark((x, self.height - 1), (0, -1), (1, 0));
         }
+        tunnels
+    }
+
+    fn find_house_exit(&self) -> Option<(i32, i32)> {
+        self.positions(|t| *t == Tile::Door)
+            .into_iter()
+            .flat_map(|door| self.neighbours(door))
+            .find(|&(x, y)| self.is_player_passable(x, y))
     }
 
     #[cfg(test)]
@@ -49,6 +127,9 @@ impl Maze {
             height: 0,
             maze: Vec::new(),
             respawn_point: (0, 0),
+            house_exit: None,
+            tunnels: Vec::new(),
+            paths_home: HashMap::new(),
             n_dots: 0,
         }
     }
@@ -70,6 +151,47 @@ impl Maze {
         ((y % self.height) * self.width + (x % self.width)) as usize
     }
 
+    /// Get the tile at a position, wrapping around the edges of the maze
+    pub fn tile(&self, x: i32, y: i32) -> Tile {
+        self.maze[self.index(x, y)]
+    }
+
+    /// Get the positions of all tiles matching `predicate`
+    pub fn positions(&self, predicate: impl Fn(&Tile) -> bool) -> Vec<(i32, i32)> {
+        self.iter()
+            .enumerate()
+            .filter(|(_, tile)| predicate(tile))
+            .map(|(i, _)| (i as i32 % self.width, i as i32 / self.width))
+            .collect()
+    }
+
+    /// Get the four tiles adjacent to a position, wrapping around the edges of the maze
+    pub fn neighbours(&self, (x, y): (i32, i32)) -> [(i32, i32); 4] {
+        [
+            ((x + self.width - 1) % self.width, y),
+            ((x + 1) % self.width, y),
+            (x, (y + self.height - 1) % self.height),
+            (x, (y + 1) % self.height),
+        ]
+    }
+
+    /// The next tile on a shortest path for ghosts from `pos` to the respawn
+    /// point, or None if `pos` is the respawn point or has no way there
+    pub fn next_step_home(&self, pos: (i32, i32)) -> Option<(i32, i32)> {
+        self.paths_home.get(&pos).copied()
+    }
+
+    /// Replace a tile, e.g. to remove a dot, keeping the dot count up to date
+    pub fn set_tile(&mut self, x: i32, y: i32, tile: Tile) {
+        let index = self.index(x, y);
+        self.n_dots += (tile == Tile::Dot) as i32 - (self.maze[index] == Tile::Dot) as i32;
+        self.maze[index] = tile;
+    }
+
+    pub fn is_tunnel(&self, x: i32, y: i32) -> bool {
+        self.tunnels[self.index(x, y)]
+    }
+
     pub fn is_player_passable(&self, x: i32, y: i32) -> bool {
         player_passable(self.maze.get(self.index(x, y)).unwrap())
     }
@@ -79,7 +201,7 @@ impl Maze {
     }
 
     pub fn eat_dots(&mut self, munch: &actor::Actor) -> i32 {
-        let covering_tiles = munch.get_covering_tiles(0.45);
+        let covering_tiles = munch.get_covering_tiles(EATING_THRESHOLD);
         let mut eaten = 0;
         for (x, y) in covering_tiles {
             if self.maze.get(self.index(x, y)) == Some(&Tile::Dot) {
@@ -93,7 +215,7 @@ impl Maze {
     }
 
     pub fn eat_power_pellets(&mut self, munch: &actor::Actor) -> i32 {
-        let covering_tiles = munch.get_covering_tiles(0.45);
+        let covering_tiles = munch.get_covering_tiles(EATING_THRESHOLD);
         let mut eaten = 0;
         for (x, y) in covering_tiles {
             if self.maze.get(self.index(x, y)) == Some(&Tile::PowerPellet) {
@@ -116,7 +238,7 @@ impl std::fmt::Display for Maze {
                 let c = match tile {
                     Tile::Wall => '#',
                     Tile::Path => ' ',
-                    Tile::PlayerImpassable => '=',
+                    Tile::PlayerImpassable | Tile::Door => '=',
                     Tile::Dot => '.',
                     Tile::PowerPellet => '*',
                     Tile::Respawn => 'R',
@@ -170,13 +292,53 @@ mod tests {
     fn test_maze_to_string() {
         let maze_str = "
 #####
-# R #
+#MR #
 #=#=#
 #..*#
 #####
 ";
         let maze = config::Config::from_string(maze_str).unwrap().maze;
         let maze_display = maze.to_string();
-        pretty_assertions::assert_eq!(maze_display.trim(), maze_str.trim());
+        // Munch's spawn point is stored as a plain path tile
+        pretty_assertions::assert_eq!(maze_display.trim(), maze_str.trim().replace('M', " "));
+    }
+
+    #[test]
+    fn test_paths_home_follow_shortest_route() {
+        let maze_str = "
+#########
+#M. #   #
+### # # #
+#R    # #
+#########
+";
+        let maze = config::Config::from_string(maze_str).unwrap().maze;
+        let mut pos = (3, 1);
+        let mut path = vec![pos];
+        while let Some(next) = maze.next_step_home(pos) {
+            pos = next;
+            path.push(pos);
+        }
+        pretty_assertions::assert_eq!(path, vec![(3, 1), (3, 2), (3, 3), (2, 3), (1, 3)]);
+        // Heading straight for the respawn point from the pocket in the
+        // corner leads into a wall, so the way home goes back up and round
+        pretty_assertions::assert_eq!(maze.next_step_home((7, 3)), Some((7, 2)));
+        pretty_assertions::assert_eq!(maze.next_step_home((7, 1)), Some((6, 1)));
+        pretty_assertions::assert_eq!(maze.next_step_home((5, 1)), Some((5, 2)));
+        pretty_assertions::assert_eq!(maze.next_step_home(maze.respawn_point), None);
+    }
+
+    #[test]
+    fn test_tunnels() {
+        let maze = config::Config::from_string(include_str!("../resources/maze.txt"))
+            .unwrap()
+            .maze;
+        for x in [0, 3, 17, 20] {
+            assert!(maze.is_tunnel(x, 10));
+        }
+        // The tunnels end where the corridor opens out
+        assert!(!maze.is_tunnel(4, 10));
+        assert!(!maze.is_tunnel(16, 10));
+        assert!(!maze.is_tunnel(10, 8));
     }
 }
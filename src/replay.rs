@@ -0,0 +1,306 @@
+use crate::{actor, game_logic};
+
+const FORMAT: &str = "munch-replay";
+const VERSION: u32 = 2;
+
+/// Munch's direction changing at the start of a tick
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ReplayEvent {
+    pub tick: u64,
+    pub direction: actor::Direction,
+}
+
+/// The inputs of a single game, which will reproduce it exactly when played
+/// back through the same sequence of ticks
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Replay {
+    /// Seed of the game's random choices
+    pub seed: u64,
+    pub events: Vec<ReplayEvent>,
+    /// Number of ticks simulated when the recording finished
+    pub length: u64,
+}
+
+fn direction_name(direction: actor::Direction) -> &'static str {
+    match direction {
+        actor::Direction::Still => "Still",
+        actor::Direction::Up => "Up",
+        actor::Direction::Down => "Down",
+        actor::Direction::Left => "Left",
+        actor::Direction::Right => "Right",
+    }
+}
+
+fn parse_direction(s: &str) -> Result<actor::Direction, String> {
+    match s {
+        "Still" => Ok(actor::Direction::Still),
+        "Up" => Ok(actor::Direction::Up),
+        "Down" => Ok(actor::Direction::Down),
+        "Left" => Ok(actor::Direction::Left),
+        "Right" => Ok(actor::Direction::Right),
+        _ => Err(format!("Unknown direction '{}'", s)),
+    }
+}
+
+impl Replay {
+    pub fn new() -> Self {
+        Replay::default()
+    }
+
+    /// Record the direction Munch is steering in before a tick is simulated.
+    /// Only changes of direction are stored, compared with the direction that
+    /// playback will be steering in. Respawning stops Munch, so an event from
+    /// before the last respawn no longer applies.
+    pub fn record(&mut self, game: &game_logic::GameLogic) {
+        let direction = game.direction();
+        let last_direction = self
+            .events
+            .last()
+            .filter(|e| e.tick >= game.respawn_tick())
+            .map_or(actor::Direction::Still, |e| e.direction);
+        if direction != last_direction {
+            self.events.push(ReplayEvent {
+                tick: game.tick_count,
+                direction,
+            });
+        }
+        self.length = game.tick_count;
+    }
+
+    pub fn from_string(s: &str) -> Result<Self, String> {
+        let mut lines = s.lines().enumerate();
+        match lines
+            .next()
+            .and_then(|(_, header)| header.trim().split_once(' '))
+        {
+            Some((FORMAT, version)) if version == VERSION.to_string() => {}
+            Some((FORMAT, version)) => {
+                return Err(format!(
+                    "Unsupported replay version {}, expected {}",
+                    version, VERSION
+                ))
+            }
+            _ => return Err(format!("Missing '{} {}' header", FORMAT, VERSION)),
+        }
+        let mut replay = Replay::new();
+        for (i, line) in lines {
+            let line = line.trim();
+            if line.is_empty() {
+                continue;
+            }
+            let error = |e: String| format!("Error on line {}: {}", i + 1, e);
+            let (key, value) = line
+                .split_once(' ')
+                .ok_or_else(|| error(format!("Expected two fields, found '{}'", line)))?;
+            if key == "length" {
+                replay.length = value
+                    .parse()
+                    .map_err(|e| error(format!("Invalid length '{}': {}", value, e)))?;
+                continue;
+            }
+            if key == "seed" {
+                replay.seed = value
+                    .parse()
+                    .map_err(|e| error(format!("Invalid seed '{}': {}", value, e)))?;
+                continue;
+            }
+            let tick = key
+                .parse()
+                .map_err(|e| error(format!("Invalid tick '{}': {}", key, e)))?;
+            if replay.events.last().is_some_and(|e| e.tick > tick) {
+                return Err(error(format!("Tick {} is out of order", tick)));
+            }
+            let direction = parse_direction(value).map_err(error)?;
+            replay.events.push(ReplayEvent { tick, direction });
+        }
+        Ok(replay)
+    }
+
+    pub fn from_file(path: &std::path::Path) -> Result<Self, String> {
+        let s = std::fs::read_to_string(path)
+            .map_err(|e| format!("Could not read {}: {}", path.display(), e))?;
+        Self::from_string(&s)
+    }
+
+    pub fn save_to_file(&self, path: &std::path::Path) -> Result<(), String> {
+        std::fs::write(path, self.to_string())
+            .map_err(|e| format!("Could not write {}: {}", path.display(), e))
+    }
+}
+
+impl std::fmt::Display for Replay {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        writeln!(f, "{} {}", FORMAT, VERSION)?;
+        writeln!(f, "seed {}", self.seed)?;
+        writeln!(f, "length {}", self.length)?;
+        for event in &self.events {
+            writeln!(f, "{} {}", event.tick, direction_name(event.direction))?;
+        }
+        Ok(())
+    }
+}
+
+/// Feeds the events of a replay into a game as it is simulated
+pub struct Playback {
+    replay: Replay,
+    next_event: usize,
+}
+
+impl Playback {
+    pub fn new(replay: Replay) -> Self {
+        Playback {
+            replay,
+            next_event: 0,
+        }
+    }
+
+    /// Steer Munch as recorded for the next tick of `game`
+    pub fn apply(&mut self, game: &mut game_logic::GameLogic) {
+        while let Some(event) = self.replay.events.get(self.next_event) {
+            if event.tick > game.tick_count {
+                break;
+            }
+            game.set_direction(event.direction);
+            self.next_event += 1;
+        }
+    }
+
+    pub fn is_finished(&self, game: &game_logic::GameLogic) -> bool {
+        game.tick_count >= self.replay.length
+    }
+}
+
+/// Play a replay through from the start of `game` without a window, seeding
+/// it from the replay. Deaths and level changes happen immediately instead of
+/// waiting for animations, which doesn't affect the outcome as no ticks pass
+/// meanwhile.
+pub fn run_headless(game: &mut game_logic::GameLogic, replay: &Replay) {
+    game.set_seed(replay.seed);
+    let mut playback = Playback::new(replay.clone());
+    while !playback.is_finished(game) && !game.is_game_over() {
+        playback.apply(game);
+        game.tick();
+        if game.munch_is_dead {
+            game.lose_life();
+        } else if game.is_level_complete() {
+            game.next_level();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config;
+
+    #[test]
+    fn test_replay_round_trip() {
+        let mut replay = Replay::new();
+        replay.events.push(ReplayEvent {
+            tick: 0,
+            direction: actor::Direction::Left,
+        });
+        replay.events.push(ReplayEvent {
+            tick: 42,
+            direction: actor::Direction::Up,
+        });
+        replay.length = 100;
+        replay.seed = 7;
+        let s = replay.to_string();
+        pretty_assertions::assert_eq!(s, "munch-replay 2\nseed 7\nlength 100\n0 Left\n42 Up\n");
+        pretty_assertions::assert_eq!(Replay::from_string(&s), Ok(replay));
+    }
+
+    #[test]
+    fn test_replay_invalid_direction() {
+        let replay = Replay::from_string("munch-replay 2\nlength 10\n5 Sideways\n");
+        pretty_assertions::assert_eq!(
+            replay,
+            Err("Error on line 3: Unknown direction 'Sideways'".to_string())
+        );
+    }
+
+    #[test]
+    fn test_replay_unsupported_version() {
+        pretty_assertions::assert_eq!(
+            Replay::from_string("munch-replay 1\nlength 10\n"),
+            Err("Unsupported replay version 1, expected 2".to_string())
+        );
+    }
+
+    #[test]
+    fn test_recorded_game_plays_back_identically() {
+        let config = config::Config::from_string(include_str!("../resources/maze.txt")).unwrap();
+        let mut game = game_logic::GameLogic::new(config.clone());
+        game.set_seed(1234);
+        let mut replay = Replay::new();
+        replay.seed = game.seed();
+        let directions = [
+            actor::Direction::Right,
+            actor::Direction::Down,
+            actor::Direction::Left,
+            actor::Direction::Up,
+        ];
+        for i in 0..3000 {
+            if i % 200 == 0 {
+                game.set_direction(directions[(i / 200) % directions.len()]);
+            }
+            replay.record(&game);
+            game.tick();
+            if game.munch_is_dead && !game.lose_life() {
+                break;
+            }
+        }
+        replay.length = game.tick_count;
+
+        let replay = Replay::from_string(&replay.to_string()).unwrap();
+        let mut played_back = game_logic::GameLogic::new(config);
+        run_headless(&mut played_back, &replay);
+        pretty_assertions::assert_eq!(played_back.tick_count, game.tick_count);
+        pretty_assertions::assert_eq!(played_back.score, game.score);
+        pretty_assertions::assert_eq!(played_back.lives, game.lives);
+        pretty_assertions::assert_eq!(played_back.munch.get_pos(), game.munch.get_pos());
+    }
+
+    #[test]
+    fn test_playback_matches_after_death() {
+        let maze_str = "
+#########
+#M..  B.#
+####R####
+#########
+";
+        let config = config::Config::from_string(maze_str).unwrap();
+        let mut game = game_logic::GameLogic::new(config.clone());
+        let mut replay = Replay::new();
+        let mut recorded = Vec::new();
+        // Keep steering right across each death, as a player holding the key would
+        while !game.is_game_over() {
+            game.set_direction(actor::Direction::Right);
+            replay.record(&game);
+            game.tick();
+            recorded.push((game.tick_count, game.munch.get_pos(), game.lives));
+            if game.munch_is_dead {
+                game.lose_life();
+            }
+        }
+        replay.length = game.tick_count;
+
+        let mut played_back = game_logic::GameLogic::new(config);
+        let mut playback = Playback::new(replay);
+        let mut positions = Vec::new();
+        while !playback.is_finished(&played_back) && !played_back.is_game_over() {
+            playback.apply(&mut played_back);
+            played_back.tick();
+            positions.push((
+                played_back.tick_count,
+                played_back.munch.get_pos(),
+                played_back.lives,
+            ));
+            if played_back.munch_is_dead {
+                played_back.lose_life();
+            }
+        }
+        pretty_assertions::assert_eq!(positions, recorded);
+    }
+}
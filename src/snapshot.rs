@@ -0,0 +1,381 @@
+use std::collections::HashMap;
+
+use crate::{actor, fruit, ghost, ghost_house};
+
+const FORMAT: &str = "munch-snapshot";
+const VERSION: u32 = 1;
+
+const DIRECTIONS: [actor::Direction; 5] = [
+    actor::Direction::Still,
+    actor::Direction::Up,
+    actor::Direction::Down,
+    actor::Direction::Left,
+    actor::Direction::Right,
+];
+const PERSONALITIES: [ghost::Personality; 4] = [
+    ghost::Personality::Blinky,
+    ghost::Personality::Pinky,
+    ghost::Personality::Inky,
+    ghost::Personality::Clyde,
+];
+const MODES: [ghost::Mode; 6] = [
+    ghost::Mode::Chase,
+    ghost::Mode::Scatter,
+    ghost::Mode::Frightened,
+    ghost::Mode::Eaten,
+    ghost::Mode::InHouse,
+    ghost::Mode::LeavingHouse,
+];
+const FRUITS: [fruit::Kind; 5] = [
+    fruit::Kind::Cherry,
+    fruit::Kind::Strawberry,
+    fruit::Kind::Orange,
+    fruit::Kind::Apple,
+    fruit::Kind::Melon,
+];
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct ActorState {
+    pub x: i32,
+    pub y: i32,
+    pub progress: f32,
+    pub direction: actor::Direction,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct GhostState {
+    pub personality: ghost::Personality,
+    pub mode: ghost::Mode,
+    pub actor: ActorState,
+}
+
+/// A game part way through, which can be resumed given the same campaign of
+/// mazes. Created by `GameLogic::snapshot` and loaded by `GameLogic::restore`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Snapshot {
+    pub level: u32,
+    pub score: u32,
+    pub lives: u32,
+    pub tick_count: u64,
+    pub seed: u64,
+    /// State of the game's random number generator
+    pub rng_state: u64,
+    /// Direction Munch is being steered in
+    pub direction: actor::Direction,
+    pub next_extra_life: Option<u32>,
+    pub is_energised: bool,
+    pub energised_timer: f32,
+    pub ghosts_eaten: u32,
+    pub schedule_phase: usize,
+    pub schedule_timer: f32,
+    pub ghost_house: ghost_house::GhostHouse,
+    pub freeze_timer: f32,
+    pub fruits_spawned: usize,
+    pub fruit_history: Vec<fruit::Kind>,
+    /// The bonus fruit showing and its time left, if any
+    pub fruit: Option<(fruit::Kind, f32)>,
+    pub munch: ActorState,
+    pub ghosts: Vec<GhostState>,
+    /// Rows of the maze as drawn by `Maze`'s `Display`, showing the dots left
+    pub maze: Vec<String>,
+}
+
+fn parse<T: std::str::FromStr>(value: &str, name: &str) -> Result<T, String>
+where
+    T::Err: std::fmt::Display,
+{
+    value
+        .parse()
+        .map_err(|e| format!("Invalid {} '{}': {}", name, value, e))
+}
+
+/// Parse one of `variants` from its name
+fn parse_variant<T: Copy + std::fmt::Debug>(value: &str, variants: &[T]) -> Result<T, String> {
+    variants
+        .iter()
+        .copied()
+        .find(|v| format!("{:?}", v) == value)
+        .ok_or_else(|| format!("Unknown value '{}'", value))
+}
+
+fn parse_actor(fields: &[&str]) -> Result<ActorState, String> {
+    match fields {
+        [x, y, progress, direction] => Ok(ActorState {
+            x: parse(x, "x")?,
+            y: parse(y, "y")?,
+            progress: parse(progress, "progress")?,
+            direction: parse_variant(direction, &DIRECTIONS)?,
+        }),
+        _ => Err(format!("Expected 4 actor fields, found {}", fields.len())),
+    }
+}
+
+/// The fields of each line of a snapshot, by the key starting the line
+struct Lines<'a> {
+    lines: HashMap<&'a str, (usize, Vec<&'a str>)>,
+}
+
+impl<'a> Lines<'a> {
+    /// Parse the fields of the line starting with `key`, which must have
+    /// `count` fields
+    fn get<T>(
+        &self,
+        key: &str,
+        count: usize,
+        parse: impl FnOnce(&[&'a str]) -> Result<T, String>,
+    ) -> Result<T, String> {
+        let (i, fields) = self
+            .lines
+            .get(key)
+            .ok_or_else(|| format!("Missing '{}' line", key))?;
+        let error = |e: String| format!("Error on line {}: {}", i + 1, e);
+        if fields.len() != count {
+            return Err(error(format!(
+                "Expected {} fields after '{}', found {}",
+                count,
+                key,
+                fields.len()
+            )));
+        }
+        parse(fields).map_err(error)
+    }
+
+    fn get_one<T: std::str::FromStr>(&self, key: &str) -> Result<T, String>
+    where
+        T::Err: std::fmt::Display,
+    {
+        self.get(key, 1, |fields| parse(fields[0], key))
+    }
+}
+
+impl Snapshot {
+    pub fn from_string(s: &str) -> Result<Self, String> {
+        let mut lines = s.lines().enumerate();
+        match lines
+            .next()
+            .and_then(|(_, header)| header.trim().split_once(' '))
+        {
+            Some((FORMAT, version)) if version == VERSION.to_string() => {}
+            Some((FORMAT, version)) => {
+                return Err(format!(
+                    "Unsupported snapshot version {}, expected {}",
+                    version, VERSION
+                ))
+            }
+            _ => return Err(format!("Missing '{} {}' header", FORMAT, VERSION)),
+        }
+        let mut fields = Lines {
+            lines: HashMap::new(),
+        };
+        let mut ghosts = Vec::new();
+        let mut maze = None;
+        for (i, line) in lines.by_ref() {
+            let mut words = line.split_whitespace();
+            let Some(key) = words.next() else {
+                continue;
+            };
+            let words = words.collect::<Vec<_>>();
+            match key {
+                "maze" => {
+                    maze = Some(Vec::new());
+                    break;
+                }
+                "ghost" => ghosts.push((i, words)),
+                _ => {
+                    if fields.lines.insert(key, (i, words)).is_some() {
+                        return Err(format!("Error on line {}: Repeated '{}' line", i + 1, key));
+                    }
+                }
+            }
+        }
+        let mut maze = maze.ok_or("Missing 'maze' section")?;
+        maze.extend(
+            lines
+                .map(|(_, line)| line.to_string())
+                .filter(|line| !line.is_empty()),
+        );
+        let ghosts = ghosts
+            .into_iter()
+            .map(|(i, words)| {
+                let ghost = match words.as_slice() {
+                    [personality, mode, actor @ ..] => Ok(GhostState {
+                        personality: parse_variant(personality, &PERSONALITIES)?,
+                        mode: parse_variant(mode, &MODES)?,
+                        actor: parse_actor(actor)?,
+                    }),
+                    _ => Err("Expected a personality, mode and actor fields".to_string()),
+                };
+                ghost.map_err(|e| format!("Error on line {}: {}", i + 1, e))
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(Snapshot {
+            level: fields.get_one("level")?,
+            score: fields.get_one("score")?,
+            lives: fields.get_one("lives")?,
+            tick_count: fields.get_one("tick")?,
+            seed: fields.get_one("seed")?,
+            rng_state: fields.get_one("rng")?,
+            direction: fields.get("direction", 1, |f| parse_variant(f[0], &DIRECTIONS))?,
+            next_extra_life: fields.get("extra_life", 1, |f| match f[0] {
+                "none" => Ok(None),
+                score => parse(score, "score").map(Some),
+            })?,
+            is_energised: fields.get("energised", 3, |f| parse(f[0], "flag"))?,
+            energised_timer: fields.get("energised", 3, |f| parse(f[1], "timer"))?,
+            ghosts_eaten: fields.get("energised", 3, |f| parse(f[2], "ghost count"))?,
+            schedule_phase: fields.get("schedule", 2, |f| parse(f[0], "phase"))?,
+            schedule_timer: fields.get("schedule", 2, |f| parse(f[1], "timer"))?,
+            ghost_house: fields.get("ghost_house", 2, |f| {
+                Ok(ghost_house::GhostHouse {
+                    dot_counter: parse(f[0], "dot counter")?,
+                    idle_timer: parse(f[1], "timer")?,
+                })
+            })?,
+            freeze_timer: fields.get_one("freeze")?,
+            fruits_spawned: fields.get_one("fruits_spawned")?,
+            fruit_history: match fields.lines.get("fruit_history") {
+                Some((i, words)) => words
+                    .iter()
+                    .map(|w| parse_variant(w, &FRUITS))
+                    .collect::<Result<_, _>>()
+                    .map_err(|e| format!("Error on line {}: {}", i + 1, e))?,
+                None => return Err("Missing 'fruit_history' line".to_string()),
+            },
+            fruit: if fields.lines.contains_key("fruit") {
+                Some(fields.get("fruit", 2, |f| {
+                    Ok((parse_variant(f[0], &FRUITS)?, parse(f[1], "timer")?))
+                })?)
+            } else {
+                None
+            },
+            munch: fields.get("munch", 4, parse_actor)?,
+            ghosts,
+            maze,
+        })
+    }
+
+    pub fn from_file(path: &std::path::Path) -> Result<Self, String> {
+        let s = std::fs::read_to_string(path)
+            .map_err(|e| format!("Could not read {}: {}", path.display(), e))?;
+        Self::from_string(&s)
+    }
+
+    pub fn save_to_file(&self, path: &std::path::Path) -> Result<(), String> {
+        std::fs::write(path, self.to_string())
+            .map_err(|e| format!("Could not write {}: {}", path.display(), e))
+    }
+}
+
+fn write_actor(f: &mut std::fmt::Formatter, actor: &ActorState) -> std::fmt::Result {
+    write!(
+        f,
+        "{} {} {} {:?}",
+        actor.x, actor.y, actor.progress, actor.direction
+    )
+}
+
+impl std::fmt::Display for Snapshot {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        writeln!(f, "{} {}", FORMAT, VERSION)?;
+        writeln!(f, "level {}", self.level)?;
+        writeln!(f, "score {}", self.score)?;
+        writeln!(f, "lives {}", self.lives)?;
+        writeln!(f, "tick {}", self.tick_count)?;
+        writeln!(f, "seed {}", self.seed)?;
+        writeln!(f, "rng {}", self.rng_state)?;
+        writeln!(f, "direction {:?}", self.direction)?;
+        match self.next_extra_life {
+            Some(score) => writeln!(f, "extra_life {}", score)?,
+            None => writeln!(f, "extra_life none")?,
+        }
+        writeln!(
+            f,
+            "energised {} {} {}",
+            self.is_energised, self.energised_timer, self.ghosts_eaten
+        )?;
+        writeln!(
+            f,
+            "schedule {} {}",
+            self.schedule_phase, self.schedule_timer
+        )?;
+        writeln!(
+            f,
+            "ghost_house {} {}",
+            self.ghost_house.dot_counter, self.ghost_house.idle_timer
+        )?;
+        writeln!(f, "freeze {}", self.freeze_timer)?;
+        writeln!(f, "fruits_spawned {}", self.fruits_spawned)?;
+        write!(f, "fruit_history")?;
+        for kind in &self.fruit_history {
+            write!(f, " {:?}", kind)?;
+        }
+        writeln!(f)?;
+        if let Some((kind, timer)) = self.fruit {
+            writeln!(f, "fruit {:?} {}", kind, timer)?;
+        }
+        write!(f, "munch ")?;
+        write_actor(f, &self.munch)?;
+        writeln!(f)?;
+        for ghost in &self.ghosts {
+            write!(f, "ghost {:?} {:?} ", ghost.personality, ghost.mode)?;
+            write_actor(f, &ghost.actor)?;
+            writeln!(f)?;
+        }
+        writeln!(f, "maze")?;
+        for row in &self.maze {
+            writeln!(f, "{}", row)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{bot, config, game_logic};
+
+    /// Let the bot play for `ticks` ticks
+    fn play(game: &mut game_logic::GameLogic, ticks: u64) {
+        for _ in 0..ticks {
+            game.set_direction(bot::choose_direction(game));
+            game.tick();
+            if game.munch_is_dead {
+                game.lose_life();
+            }
+        }
+    }
+
+    #[test]
+    fn test_restored_game_plays_on_identically() {
+        let config = config::Config::from_string(include_str!("../resources/maze.txt")).unwrap();
+        let mut game = game_logic::GameLogic::new(config.clone());
+        game.set_seed(99);
+        play(&mut game, 2400);
+        let snapshot = Snapshot::from_string(&game.snapshot().to_string()).unwrap();
+        pretty_assertions::assert_eq!(snapshot, game.snapshot());
+
+        let mut restored = game_logic::GameLogic::new(config);
+        restored.restore(&snapshot).unwrap();
+        pretty_assertions::assert_eq!(restored.snapshot(), snapshot);
+        play(&mut game, 1200);
+        play(&mut restored, 1200);
+        pretty_assertions::assert_eq!(restored.snapshot(), game.snapshot());
+    }
+
+    #[test]
+    fn test_restore_rejects_other_maze() {
+        let mut game = game_logic::GameLogic::new(
+            config::Config::from_string(include_str!("../resources/maze.txt")).unwrap(),
+        );
+        let mut snapshot = game.snapshot();
+        snapshot.maze[1].replace_range(1..2, "#");
+        pretty_assertions::assert_eq!(
+            game.restore(&snapshot),
+            Err("Maze doesn't match the maze for level 1 at (1, 1)".to_string())
+        );
+        pretty_assertions::assert_eq!(
+            Snapshot::from_string("munch-snapshot 1\nlevel 1\nmaze\n"),
+            Err("Missing 'score' line".to_string())
+        );
+    }
+}
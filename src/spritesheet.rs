@@ -2,7 +2,9 @@ use ggez::glam;
 use ggez::graphics;
 use ggez::Context;
 
-use crate::{actor, config, ghost};
+use munch::{actor, fruit, ghost};
+
+use crate::window;
 
 pub struct SpriteSheet {
     pub image: graphics::Image,
@@ -55,8 +57,8 @@ impl SpriteSheet {
             self.sprite_v_height - 0.002,
         );
         let dest_scale = glam::Vec2::new(
-            (config::TILE_SIZE - 4.0) / SPRITE_WIDTH as f32,
-            (config::TILE_SIZE - 4.0) / SPRITE_HEIGHT as f32,
+            (window::TILE_SIZE - 4.0) / SPRITE_WIDTH as f32,
+            (window::TILE_SIZE - 4.0) / SPRITE_HEIGHT as f32,
         );
         let adapted_dest = glam::Vec2::new(dest.x + 2.0, dest.y + 2.0);
         canvas.draw(
@@ -89,6 +91,17 @@ impl SpriteSheet {
         }
     }
 
+    pub fn draw_fruit(&self, canvas: &mut graphics::Canvas, kind: fruit::Kind, pos: glam::Vec2) {
+        let i = match kind {
+            fruit::Kind::Cherry => 0,
+            fruit::Kind::Strawberry => 1,
+            fruit::Kind::Orange => 2,
+            fruit::Kind::Apple => 3,
+            fruit::Kind::Melon => 4,
+        };
+        self.draw_sprite(canvas, i, 7, pos)
+    }
+
     pub fn draw_munch_death(&self, canvas: &mut graphics::Canvas, pos: glam::Vec2, frame: usize) {
         let anim_frame = ((frame / (DEATH_ANIM_FPS)) % 10) as u32;
         let sprite_sheet_x = anim_frame % 4;
@@ -96,19 +109,24 @@ impl SpriteSheet {
         self.draw_sprite(canvas, 12 + sprite_sheet_x, 4 + sprite_sheet_y, pos)
     }
 
+    /// Frightened ghosts are drawn white instead of blue if `flash` is set
     pub fn draw_ghost(
         &self,
         canvas: &mut graphics::Canvas,
         ghost: &ghost::Ghost,
         pos: glam::Vec2,
         frame: usize,
+        flash: bool,
     ) {
         match ghost.mode {
-            ghost::Mode::Chase => {
-                self.draw_ghost_chase(canvas, ghost, pos, frame);
+            ghost::Mode::Chase
+            | ghost::Mode::Scatter
+            | ghost::Mode::InHouse
+            | ghost::Mode::LeavingHouse => {
+                self.draw_ghost_normal(canvas, ghost, pos, frame);
             }
-            ghost::Mode::Scatter => {
-                self.draw_ghost_scatter(canvas, ghost, pos, frame);
+            ghost::Mode::Frightened => {
+                self.draw_ghost_frightened(canvas, ghost, pos, frame, flash);
             }
             ghost::Mode::Eaten => {
                 self.draw_ghost_eaten(canvas, ghost, pos);
@@ -116,7 +134,7 @@ impl SpriteSheet {
         }
     }
 
-    fn draw_ghost_chase(
+    fn draw_ghost_normal(
         &self,
         canvas: &mut graphics::Canvas,
         ghost: &ghost::Ghost,
@@ -140,15 +158,16 @@ impl SpriteSheet {
         }
     }
 
-    fn draw_ghost_scatter(
+    fn draw_ghost_frightened(
         &self,
         canvas: &mut graphics::Canvas,
         ghost: &ghost::Ghost,
         pos: glam::Vec2,
         frame: usize,
+        flash: bool,
     ) {
         let anim_frame = ((frame / ANIM_FPS) % 3) as u32;
-        let flash_frame = ((frame / (ANIM_FPS * 2)) % 2) as u32;
+        let flash_frame = flash as u32;
         match ghost.actor.move_direction {
             actor::Direction::Still | actor::Direction::Right => {
                 self.draw_sprite(canvas, anim_frame, 4 + flash_frame, pos)
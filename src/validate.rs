@@ -0,0 +1,176 @@
+use pathfinding::directed::bfs::bfs_reach;
+
+use crate::{config, ghost, maze};
+
+/// A problem which makes a maze unwinnable or broken, with positions given as
+/// (x, y) maze coordinates
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ValidationError {
+    /// Munch can't reach a dot from the player spawn
+    UnreachableDot { pos: (i32, i32) },
+    /// Munch can't reach a power pellet from the player spawn
+    UnreachablePowerPellet { pos: (i32, i32) },
+    /// A ghost can't reach the respawn tile from its spawn, so can't return when eaten
+    UnreachableRespawn {
+        personality: ghost::Personality,
+        pos: (i32, i32),
+    },
+    /// A passable tile on the edge of the maze wraps around to an impassable one
+    DeadEndTunnel { pos: (i32, i32), exit: (i32, i32) },
+}
+
+impl std::fmt::Display for ValidationError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            ValidationError::UnreachableDot { pos } => {
+                write!(f, "dot at {:?} can't be reached by Munch", pos)
+            }
+            ValidationError::UnreachablePowerPellet { pos } => {
+                write!(f, "power pellet at {:?} can't be reached by Munch", pos)
+            }
+            ValidationError::UnreachableRespawn { personality, pos } => write!(
+                f,
+                "{:?} at {:?} can't reach the respawn tile",
+                personality, pos
+            ),
+            ValidationError::DeadEndTunnel { pos, exit } => write!(
+                f,
+                "tunnel at {:?} wraps around to impassable tile at {:?}",
+                pos, exit
+            ),
+        }
+    }
+}
+
+/// Tiles reachable from `start`, moving only through tiles matching `passable`
+fn reachable(
+    maze: &maze::Maze,
+    start: (i32, i32),
+    passable: fn(&maze::Tile) -> bool,
+) -> std::collections::HashSet<(i32, i32)> {
+    bfs_reach(start, |&pos| {
+        maze.neighbours(pos)
+            .into_iter()
+            .filter(|&(x, y)| passable(&maze.tile(x, y)))
+    })
+    .collect()
+}
+
+fn check_tunnels(maze: &maze::Maze, errors: &mut Vec<ValidationError>) {
+    let mut check = |pos: (i32, i32), exit: (i32, i32)| {
+        let tile = maze.tile(pos.0, pos.1);
+        let exit_tile = maze.tile(exit.0, exit.1);
+        let dead_end = (maze::player_passable(&tile) && !maze::player_passable(&exit_tile))
+            || (maze::ghost_passable(&tile) && !maze::ghost_passable(&exit_tile));
+        if dead_end {
+            errors.push(ValidationError::DeadEndTunnel { pos, exit });
+        }
+    };
+    for y in 0..maze.height {
+        check((0, y), (maze.width - 1, y));
+        check((maze.width - 1, y), (0, y));
+    }
+    for x in 0..maze.width {
+        check((x, 0), (x, maze.height - 1));
+        check((x, maze.height - 1), (x, 0));
+    }
+}
+
+/// Check that a maze can be completed: every dot and power pellet can be eaten,
+/// every ghost can return to the respawn tile, and tunnels lead somewhere
+pub fn validate(config: &config::Config) -> Result<(), Vec<ValidationError>> {
+    let maze = &config.maze;
+    let mut errors = Vec::new();
+    let player_reachable = reachable(maze, config.player_pos, maze::player_passable);
+    for pos in maze.positions(|t| *t == maze::Tile::Dot) {
+        if !player_reachable.contains(&pos) {
+            errors.push(ValidationError::UnreachableDot { pos });
+        }
+    }
+    for pos in maze.positions(|t| *t == maze::Tile::PowerPellet) {
+        if !player_reachable.contains(&pos) {
+            errors.push(ValidationError::UnreachablePowerPellet { pos });
+        }
+    }
+    for &(x, y, personality) in &config.ghosts_pos {
+        let ghost_reachable = reachable(maze, (x, y), maze::ghost_passable);
+        if !ghost_reachable.contains(&maze.respawn_point) {
+            errors.push(ValidationError::UnreachableRespawn {
+                personality,
+                pos: (x, y),
+            });
+        }
+    }
+    check_tunnels(maze, &mut errors);
+    if errors.is_empty() {
+        Ok(())
+    } else {
+        Err(errors)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_default_maze_is_valid() {
+        let config = config::Config::from_string(include_str!("../resources/maze.txt")).unwrap();
+        pretty_assertions::assert_eq!(validate(&config), Ok(()));
+    }
+
+    #[test]
+    fn test_unreachable_pickups() {
+        let maze_str = "
+#######
+#M. #.#
+#####*#
+#R   ##
+#######
+";
+        let config = config::Config::from_string(maze_str).unwrap();
+        pretty_assertions::assert_eq!(
+            validate(&config),
+            Err(vec![
+                ValidationError::UnreachableDot { pos: (5, 1) },
+                ValidationError::UnreachablePowerPellet { pos: (5, 2) },
+            ])
+        );
+    }
+
+    #[test]
+    fn test_ghost_walled_in() {
+        let maze_str = "
+#######
+#M.#B##
+#######
+###R###
+#######
+";
+        let config = config::Config::from_string(maze_str).unwrap();
+        pretty_assertions::assert_eq!(
+            validate(&config),
+            Err(vec![ValidationError::UnreachableRespawn {
+                personality: ghost::Personality::Blinky,
+                pos: (4, 1),
+            }])
+        );
+    }
+
+    #[test]
+    fn test_dead_end_tunnel() {
+        let maze_str = "
+#####
+ M.R#
+#####
+";
+        let config = config::Config::from_string(maze_str).unwrap();
+        pretty_assertions::assert_eq!(
+            validate(&config),
+            Err(vec![ValidationError::DeadEndTunnel {
+                pos: (0, 1),
+                exit: (4, 1),
+            }])
+        );
+    }
+}
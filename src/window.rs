@@ -3,10 +3,17 @@ use ggez::{Context, GameResult};
 
 use ggez::glam;
 
-use crate::{actor, config, ghost, maze, spritesheet};
+use munch::{actor, fruit, game_logic, ghost, maze};
 
+use crate::spritesheet;
+
+pub const TILE_SIZE: f32 = 48.0;
 const DOT_SCALE: f32 = 0.2;
 const POWER_PELLET_SCALE: f32 = 0.4;
+/// Number of frames between colour changes when the maze flashes
+const MAZE_FLASH_FRAMES: usize = 15;
+/// Number of most recently eaten fruits shown on the HUD
+const FRUIT_HISTORY_LEN: usize = 7;
 
 pub struct Window {
     spritesheet: spritesheet::SpriteSheet,
@@ -30,19 +37,19 @@ impl Window {
         self.frame = 0;
     }
 
-    fn draw_wall(&self, canvas: &mut Canvas, x: f32, y: f32) {
-        let rect = graphics::Rect::new(x, y, config::TILE_SIZE, config::TILE_SIZE);
+    fn draw_wall(&self, canvas: &mut Canvas, x: f32, y: f32, color: Color) {
+        let rect = graphics::Rect::new(x, y, TILE_SIZE, TILE_SIZE);
         canvas.draw(
             &graphics::Quad,
             graphics::DrawParam::new()
                 .dest(rect.point())
                 .scale(rect.size())
-                .color(Color::BLUE),
+                .color(color),
         );
     }
 
     fn draw_player_impassable(&self, canvas: &mut Canvas, x: f32, y: f32) {
-        let rect = graphics::Rect::new(x, y, config::TILE_SIZE, config::TILE_SIZE);
+        let rect = graphics::Rect::new(x, y, TILE_SIZE, TILE_SIZE);
         canvas.draw(
             &graphics::Quad,
             graphics::DrawParam::new()
@@ -53,8 +60,8 @@ impl Window {
     }
 
     fn draw_dot(&self, canvas: &mut Canvas, x: f32, y: f32) {
-        let dot_size = config::TILE_SIZE * DOT_SCALE;
-        let offset = (config::TILE_SIZE - dot_size) / 2.0;
+        let dot_size = TILE_SIZE * DOT_SCALE;
+        let offset = (TILE_SIZE - dot_size) / 2.0;
         let rect = graphics::Rect::new(x + offset, y + offset, dot_size, dot_size);
         canvas.draw(
             &graphics::Quad,
@@ -66,8 +73,8 @@ impl Window {
     }
 
     fn draw_power_pellet(&self, canvas: &mut Canvas, x: f32, y: f32) {
-        let dot_size = config::TILE_SIZE * POWER_PELLET_SCALE;
-        let offset = (config::TILE_SIZE - dot_size) / 2.0;
+        let dot_size = TILE_SIZE * POWER_PELLET_SCALE;
+        let offset = (TILE_SIZE - dot_size) / 2.0;
         let rect = graphics::Rect::new(x + offset, y + offset, dot_size, dot_size);
         canvas.draw(
             &graphics::Quad,
@@ -78,17 +85,22 @@ impl Window {
         );
     }
 
-    fn draw_maze(&self, canvas: &mut Canvas, maze: &maze::Maze) -> (f32, f32) {
-        let phys_maze_width = maze.width as f32 * config::TILE_SIZE;
-        let phys_maze_height = maze.height as f32 * config::TILE_SIZE;
+    fn draw_maze(&self, canvas: &mut Canvas, maze: &maze::Maze, flash: bool) -> (f32, f32) {
+        let wall_color = if flash && (self.frame / MAZE_FLASH_FRAMES) % 2 == 1 {
+            Color::WHITE
+        } else {
+            Color::BLUE
+        };
+        let phys_maze_width = maze.width as f32 * TILE_SIZE;
+        let phys_maze_height = maze.height as f32 * TILE_SIZE;
         let start_x = (self.width - phys_maze_width) / 2.0;
         let start_y = (self.height - phys_maze_height) / 2.0;
         for (i, tile) in maze.iter().enumerate() {
-            let x = (i % maze.width as usize) as f32 * config::TILE_SIZE + start_x;
-            let y = (i / maze.width as usize) as f32 * config::TILE_SIZE + start_y;
+            let x = (i % maze.width as usize) as f32 * TILE_SIZE + start_x;
+            let y = (i / maze.width as usize) as f32 * TILE_SIZE + start_y;
             match tile {
-                maze::Tile::Wall => self.draw_wall(canvas, x, y),
-                maze::Tile::PlayerImpassable | maze::Tile::Respawn => {
+                maze::Tile::Wall => self.draw_wall(canvas, x, y, wall_color),
+                maze::Tile::PlayerImpassable | maze::Tile::Door | maze::Tile::Respawn => {
                     self.draw_player_impassable(canvas, x, y)
                 }
                 maze::Tile::Dot => self.draw_dot(canvas, x, y),
@@ -105,13 +117,11 @@ impl Window {
         munch: &actor::Actor,
         start_x: f32,
         start_y: f32,
+        alpha: f32,
         death_in_progress: bool,
     ) {
-        let (munch_x, munch_y) = munch.get_draw_pos();
-        let pos = glam::Vec2::new(
-            munch_x * config::TILE_SIZE + start_x,
-            munch_y * config::TILE_SIZE + start_y,
-        );
+        let (munch_x, munch_y) = munch.get_interpolated_draw_pos(alpha);
+        let pos = glam::Vec2::new(munch_x * TILE_SIZE + start_x, munch_y * TILE_SIZE + start_y);
         if !death_in_progress {
             self.spritesheet
                 .draw_munch(canvas, munch.move_direction, pos, self.frame)
@@ -120,13 +130,50 @@ impl Window {
         }
     }
 
-    fn draw_ghost(&self, canvas: &mut Canvas, ghost: &ghost::Ghost, start_x: f32, start_y: f32) {
-        let (ghost_x, ghost_y) = ghost.actor.get_draw_pos();
+    fn draw_ghost(
+        &self,
+        canvas: &mut Canvas,
+        ghost: &ghost::Ghost,
+        start_x: f32,
+        start_y: f32,
+        alpha: f32,
+        flash: bool,
+    ) {
+        let (ghost_x, ghost_y) = ghost.actor.get_interpolated_draw_pos(alpha);
+        let pos = glam::Vec2::new(ghost_x * TILE_SIZE + start_x, ghost_y * TILE_SIZE + start_y);
+        self.spritesheet
+            .draw_ghost(canvas, ghost, pos, self.frame, flash);
+    }
+
+    fn draw_fruit(&self, canvas: &mut Canvas, fruit: &fruit::Fruit, start_x: f32, start_y: f32) {
+        let (x, y) = fruit.pos;
         let pos = glam::Vec2::new(
-            ghost_x * config::TILE_SIZE + start_x,
-            ghost_y * config::TILE_SIZE + start_y,
+            x as f32 * TILE_SIZE + start_x,
+            y as f32 * TILE_SIZE + start_y,
+        );
+        self.spritesheet.draw_fruit(canvas, fruit.kind, pos);
+    }
+
+    /// Draw the points for an eaten ghost or fruit over the tile it was eaten on
+    fn draw_score_popup(
+        &self,
+        canvas: &mut Canvas,
+        popup: &game_logic::ScorePopup,
+        start_x: f32,
+        start_y: f32,
+    ) {
+        let mut text = Text::new(popup.points.to_string());
+        text.set_scale(TILE_SIZE / 2.0);
+        let (x, y) = popup.pos;
+        canvas.draw(
+            &text,
+            graphics::DrawParam::from([
+                (x as f32 + 0.5) * TILE_SIZE + start_x,
+                (y as f32 + 0.5) * TILE_SIZE + start_y,
+            ])
+            .offset([0.5, 0.5])
+            .color(Color::CYAN),
         );
-        self.spritesheet.draw_ghost(canvas, ghost, pos, self.frame);
     }
 
     fn draw_fps(&self, ctx: &Context, canvas: &mut Canvas) {
@@ -146,24 +193,86 @@ impl Window {
         );
     }
 
+    fn draw_level(&self, canvas: &mut Canvas, level: u32) {
+        let level_display = Text::new(format!("Level: {}", level));
+        canvas.draw(
+            &level_display,
+            graphics::DrawParam::from([200.0, 60.0]).color(Color::WHITE),
+        );
+    }
+
+    fn draw_lives(&self, canvas: &mut Canvas, lives: u32) {
+        let lives_display = Text::new(format!("Lives: {}", lives));
+        canvas.draw(
+            &lives_display,
+            graphics::DrawParam::from([200.0, 40.0]).color(Color::WHITE),
+        );
+    }
+
+    fn draw_fruit_history(&self, canvas: &mut Canvas, fruit_history: &[fruit::Kind]) {
+        let start = fruit_history.len().saturating_sub(FRUIT_HISTORY_LEN);
+        for (i, &kind) in fruit_history[start..].iter().enumerate() {
+            let pos = glam::Vec2::new(200.0 + i as f32 * TILE_SIZE, 80.0);
+            self.spritesheet.draw_fruit(canvas, kind, pos);
+        }
+    }
+
+    /// Draw a message centred on the maze, e.g. "READY!" or "GAME OVER"
+    fn draw_message(&self, canvas: &mut Canvas, message: &str) {
+        let mut text = Text::new(message);
+        text.set_scale(TILE_SIZE);
+        canvas.draw(
+            &text,
+            graphics::DrawParam::from([self.width / 2.0, self.height / 2.0])
+                .offset([0.5, 0.5])
+                .color(Color::YELLOW),
+        );
+    }
+
     pub fn draw(
         &mut self,
         ctx: &mut Context,
-        maze: &maze::Maze,
-        munch: &actor::Actor,
-        ghosts: &Vec<ghost::Ghost>,
-        score: u32,
+        game_logic: &game_logic::GameLogic,
+        alpha: f32,
         death_in_progress: bool,
+        level_complete: bool,
+        message: Option<&str>,
     ) -> GameResult {
         let mut canvas = graphics::Canvas::from_frame(ctx, Color::BLACK);
         canvas.set_sampler(graphics::Sampler::nearest_clamp());
-        let (start_x, start_y) = self.draw_maze(&mut canvas, maze);
-        self.draw_munch(&mut canvas, munch, start_x, start_y, death_in_progress);
-        for ghost in ghosts {
-            self.draw_ghost(&mut canvas, ghost, start_x, start_y);
+        let (start_x, start_y) = self.draw_maze(&mut canvas, &game_logic.maze, level_complete);
+        if let Some(fruit) = &game_logic.fruit {
+            self.draw_fruit(&mut canvas, fruit, start_x, start_y);
+        }
+        // Munch is hidden by the score while the game freezes after eating a ghost
+        if !game_logic.is_frozen() {
+            self.draw_munch(
+                &mut canvas,
+                &game_logic.munch,
+                start_x,
+                start_y,
+                alpha,
+                death_in_progress,
+            );
+        }
+        // The ghosts disappear while the maze flashes between levels
+        if !level_complete {
+            let flash = game_logic.frightened_ghosts_flash();
+            for ghost in &game_logic.ghosts {
+                self.draw_ghost(&mut canvas, ghost, start_x, start_y, alpha, flash);
+            }
+        }
+        for popup in &game_logic.popups {
+            self.draw_score_popup(&mut canvas, popup, start_x, start_y);
         }
         self.draw_fps(ctx, &mut canvas);
-        self.draw_score(&mut canvas, score);
+        self.draw_score(&mut canvas, game_logic.score);
+        self.draw_lives(&mut canvas, game_logic.lives);
+        self.draw_level(&mut canvas, game_logic.level);
+        self.draw_fruit_history(&mut canvas, &game_logic.fruit_history);
+        if let Some(message) = message {
+            self.draw_message(&mut canvas, message);
+        }
         (self.frame, _) = usize::overflowing_add(self.frame, 1);
         canvas.finish(ctx)
     }